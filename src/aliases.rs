@@ -0,0 +1,165 @@
+// Saved locations, read from $XDG_CONFIG_HOME/weather/aliases.toml:
+//
+//     home = "52.52,13.405"
+//     office = "Berlin, Germany"
+//
+// An alias goes wherever a location does, on the command line and in the
+// config file. Saving coordinates pins a place whose name the service would
+// resolve to another town of the same name.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use toml::{Parser, Value};
+
+use config::Config;
+use error::{Error, Result};
+use location::Location;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aliases {
+    /// Alias name to location, sorted by name.
+    pub entries: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    /// aliases.toml next to the default config file.
+    pub fn default_path() -> Option<PathBuf> {
+        Config::default_path().map(|path| path.with_file_name("aliases.toml"))
+    }
+
+    /// Loads an alias file, a missing file has no aliases.
+    pub fn load(path: &Path) -> Result<Aliases> {
+        if !path.exists() {
+            return Ok(Aliases::default());
+        }
+        let mut buf = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut buf))
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+        Aliases::parse(&buf).map_err(|e| match e {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            e                  => e,
+        })
+    }
+
+    pub fn parse(s: &str) -> Result<Aliases> {
+        let mut parser = Parser::new(s);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None        => {
+                let err = &parser.errors[0];
+                let (line, col) = parser.to_linecol(err.lo);
+                return Err(Error::Config(format!("{}:{}: {}", line + 1, col + 1, err.desc)));
+            }
+        };
+
+        let mut entries = BTreeMap::new();
+        for (name, value) in table {
+            match value {
+                Value::String(location) => entries.insert(name, location),
+                _ => return Err(Error::Config(format!("alias `{}` must be a string", name))),
+            };
+        }
+        Ok(Aliases { entries })
+    }
+
+    /// Writes the aliases, creating the directory if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let err = |e: ::std::io::Error| Error::Config(format!("{}: {}", path.display(), e));
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(err)?;
+        }
+        let mut f = File::create(path).map_err(err)?;
+        writeln!(f, "# Saved locations, see `weather alias`.").map_err(err)?;
+        for (name, location) in &self.entries {
+            writeln!(f, "{} = {}", name, Value::String(location.clone())).map_err(err)?;
+        }
+        Ok(())
+    }
+
+    /// Adds or replaces an alias. Names are a letter, then letters, digits,
+    /// `-` and `_`, so that they never look like another kind of location;
+    /// the location is checked as it would be on the command line.
+    pub fn add(&mut self, name: &str, location: &str) -> Result<()> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        let plain = name.starts_with(|c: char| c.is_ascii_alphabetic()) && name.chars().all(valid);
+        if !plain || !matches!(Location::parse(name), Ok(Location::Name(_))) {
+            return Err(Error::Usage(format!("alias names are a letter, then letters, digits, - and _: {}", name)));
+        }
+        let location = location.trim();
+        if location.is_empty() {
+            return Err(Error::Usage(format!("no location for alias {}", name)));
+        }
+        Location::parse(location)?;
+        self.entries.insert(name.to_string(), location.to_string());
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        match self.entries.remove(name) {
+            Some(_) => Ok(()),
+            None    => Err(Error::Usage(format!("no such alias: {}", name))),
+        }
+    }
+
+    /// The location saved as `name`, if it is an alias.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(|l| &l[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn parse_and_resolve() {
+        let aliases = Aliases::parse(r#"
+            home = "52.52,13.405"
+            datacenter-fra = "Frankfurt am Main, DE"
+        "#).unwrap();
+
+        assert_eq!(aliases.get("home"), Some("52.52,13.405"));
+        assert_eq!(aliases.get("datacenter-fra"), Some("Frankfurt am Main, DE"));
+        assert_eq!(aliases.get("Berlin"), None);
+
+        assert!(Aliases::parse("home = 42").is_err());
+        assert!(Aliases::parse("home = ").is_err());
+    }
+
+    #[test]
+    fn add_remove_and_save() {
+        let mut aliases = Aliases::default();
+        aliases.add("office", " Berlin, \"Mitte\" ").unwrap();
+        aliases.add("home", "52.52,13.405").unwrap();
+        aliases.add("home", "48.14,11.58").unwrap();
+        assert!(aliases.add("new york", "40.7,-74.0").is_err());
+        assert!(aliases.add("", "40.7,-74.0").is_err());
+        assert!(aliases.add("nyc", " ").is_err());
+        // checked as on the command line
+        assert!(aliases.add("north-pole", "91,0").is_err());
+        assert!(aliases.add("router", "192.168.1.1").is_err());
+        assert!(aliases.add("fra", "iata:EDDF").is_err());
+        // names that look like a location
+        assert!(aliases.add("10115", "Berlin").is_err());
+        assert!(aliases.add("-33", "Sydney").is_err());
+        assert!(aliases.get("north-pole").is_none());
+
+        let path = env::temp_dir().join(format!("weather-aliases-{}", ::std::process::id())).join("aliases.toml");
+        aliases.save(&path).unwrap();
+        let loaded = Aliases::load(&path).unwrap();
+        assert_eq!(loaded, aliases);
+        assert_eq!(loaded.get("office"), Some("Berlin, \"Mitte\""));
+        assert_eq!(loaded.get("home"), Some("48.14,11.58"));
+
+        aliases.remove("office").unwrap();
+        assert!(aliases.remove("office").is_err());
+        assert_eq!(aliases.entries.len(), 1);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(Aliases::load(&path).unwrap(), Aliases::default());
+    }
+}
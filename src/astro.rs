@@ -0,0 +1,152 @@
+// Sun and moon, computed locally for what the providers leave out. The
+// accuracy is that of a weather report: a minute or two for the sun, a few
+// percent of illumination for the moon.
+//
+// https://en.wikipedia.org/wiki/Sunrise_equation
+// https://en.wikipedia.org/wiki/Lunar_phase
+
+use std::f64::consts::PI;
+use time::strptime;
+
+use forecast::Forecast;
+
+/// Mean length of a lunation, in days.
+static SYNODIC_MONTH: f64 = 29.530_588_853;
+/// A new moon, 2000-01-06 18:14 UTC, as a Julian day.
+static NEW_MOON: f64 = 2_451_550.26;
+
+static MOON_GLYPHS: [&'static str; 8] = ["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"];
+
+/// Julian day at 0:00 UTC of a "%Y-%m-%d" date.
+pub fn julian_day(date: &str) -> Option<f64> {
+    let tm = strptime(date, "%Y-%m-%d").ok()?;
+    Some(tm.to_timespec().sec as f64 / 86_400.0 + 2_440_587.5)
+}
+
+/// Age in the lunar cycle at noon UTC, 0 is new moon and 0.5 full moon.
+pub fn moon_phase(jd: f64) -> f64 {
+    ((jd + 0.5 - NEW_MOON) / SYNODIC_MONTH).rem_euclid(1.0)
+}
+
+/// Lit fraction of the moon's disc, in %.
+pub fn moon_illumination(phase: f64) -> i32 {
+    ((1.0 - (2.0 * PI * phase).cos()) / 2.0 * 100.0).round() as i32
+}
+
+pub fn moon_glyph(phase: f64) -> &'static str {
+    MOON_GLYPHS[(phase * 8.0).round() as usize % 8]
+}
+
+/// Julian day of the solar noon and the sun's hour angle at sunrise, in
+/// degrees; `None` for the hour angle when the sun doesn't rise or set,
+/// with `Err(true)` for midnight sun.
+fn solar_day(jd: f64, lat: f64, lon: f64) -> (f64, Result<f64, bool>) {
+    let rad = PI / 180.0;
+    let n = (jd + 0.5 - 2_451_545.0 + 0.0008).round() - lon / 360.0;
+    let m = (357.5291 + 0.985_600_28 * n).rem_euclid(360.0);
+    let c = 1.9148 * (m * rad).sin() + 0.02 * (2.0 * m * rad).sin() + 0.0003 * (3.0 * m * rad).sin();
+    let lambda = (m + c + 180.0 + 102.9372).rem_euclid(360.0);
+    let transit = 2_451_545.0 + n + 0.0053 * (m * rad).sin() - 0.0069 * (2.0 * lambda * rad).sin();
+    let sin_d = (lambda * rad).sin() * (23.4397 * rad).sin();
+    let cos_d = (1.0 - sin_d * sin_d).sqrt();
+    let cos_w = ((-0.833 * rad).sin() - (lat * rad).sin() * sin_d) / ((lat * rad).cos() * cos_d);
+    match cos_w {
+        c if c > 1.0  => (transit, Err(false)),
+        c if c < -1.0 => (transit, Err(true)),
+        c             => (transit, Ok(c.acos() / rad)),
+    }
+}
+
+/// Minutes between sunrise and sunset, 0 in the polar night, 1440 under
+/// the midnight sun.
+pub fn day_length(jd: f64, lat: f64, lon: f64) -> i32 {
+    match solar_day(jd, lat, lon).1 {
+        Ok(w)      => (w / 360.0 * 2.0 * 1440.0).round() as i32,
+        Err(true)  => 1440,
+        Err(false) => 0,
+    }
+}
+
+/// Local sunrise and sunset as "HH:MM", `utc_offset` in seconds.
+pub fn sun_times(jd: f64, lat: f64, lon: f64, utc_offset: i32) -> (Option<String>, Option<String>) {
+    let (transit, w) = solar_day(jd, lat, lon);
+    let w = match w {
+        Ok(w)  => w,
+        Err(_) => return (None, None),
+    };
+    let clock = |t: f64| {
+        let minutes = (((t - jd) * 1440.0).round() as i32 + utc_offset / 60).rem_euclid(1440);
+        Some(format!("{:02}:{:02}", minutes / 60, minutes % 60))
+    };
+    (clock(transit - w / 360.0), clock(transit + w / 360.0))
+}
+
+/// Minutes the `day`th day is longer than the one before. The day before
+/// the forecast is computed, the difference of two computed lengths being
+/// more accurate than that of a computed and a provided one.
+pub fn day_length_change(forecast: &Forecast, day: usize) -> Option<i32> {
+    let today = forecast.days.get(day)?;
+    match day.checked_sub(1).and_then(|d| forecast.days.get(d)) {
+        Some(yesterday) => Some(today.astronomy.day_length()? - yesterday.astronomy.day_length()?),
+        None            => {
+            let (lat, lon) = forecast.coordinates?;
+            let jd = julian_day(&today.date)?;
+            Some(day_length(jd, lat, lon) - day_length(jd - 1.0, lat, lon))
+        }
+    }
+}
+
+/// Fills in the moon phase and illumination of every day, and the sunrise
+/// and sunset when the provider has neither but the place is known.
+pub fn complete(forecast: &mut Forecast) {
+    let (coordinates, utc_offset) = (forecast.coordinates, forecast.utc_offset);
+    for day in forecast.days.iter_mut() {
+        let jd = match julian_day(&day.date) {
+            Some(jd) => jd,
+            None     => continue,
+        };
+        let astronomy = &mut day.astronomy;
+        let phase = *astronomy.moon_phase.get_or_insert_with(|| moon_phase(jd));
+        astronomy.moon_illumination.get_or_insert_with(|| moon_illumination(phase));
+        if let (None, None, Some((lat, lon)), Some(offset)) =
+            (&astronomy.sunrise, &astronomy.sunset, coordinates, utc_offset) {
+            let (sunrise, sunset) = sun_times(jd, lat, lon, offset);
+            astronomy.sunrise = sunrise;
+            astronomy.sunset = sunset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun() {
+        // Beijing, 06:26 and 17:30 by timeanddate.com
+        let jd = julian_day("2026-10-18").unwrap();
+        assert_eq!(jd, 2_461_331.5);
+        assert_eq!(sun_times(jd, 39.9075, 116.3972, 8 * 3600),
+                   (Some("06:27".to_string()), Some("17:32".to_string())));
+        assert_eq!(day_length(jd, 39.9075, 116.3972), 665);
+        assert!(day_length(jd, 39.9075, 116.3972) < day_length(jd - 1.0, 39.9075, 116.3972));
+
+        // Tromsø, polar night and midnight sun
+        assert_eq!(sun_times(julian_day("2026-12-21").unwrap(), 69.65, 18.96, 3600), (None, None));
+        assert_eq!(day_length(julian_day("2026-12-21").unwrap(), 69.65, 18.96), 0);
+        assert_eq!(day_length(julian_day("2026-06-21").unwrap(), 69.65, 18.96), 1440);
+    }
+
+    #[test]
+    fn moon() {
+        // full moon on 2026-10-26, new moon on 2026-11-09
+        let full = moon_phase(julian_day("2026-10-26").unwrap());
+        assert!((full - 0.5).abs() < 0.03, "{}", full);
+        assert_eq!(moon_glyph(full), "🌕");
+        assert!(moon_illumination(full) > 98);
+        let new = moon_phase(julian_day("2026-11-09").unwrap());
+        assert!(!(0.03..=0.97).contains(&new), "{}", new);
+        assert_eq!(moon_illumination(0.25), 50);
+        assert_eq!(moon_glyph(0.25), "🌓");
+    }
+}
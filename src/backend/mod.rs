@@ -0,0 +1,238 @@
+// Weather data sources. Each backend turns a `Query` into a `Forecast`.
+
+use std::io::prelude::*;
+use std::panic;
+use std::thread;
+use rustc_serialize::json::Json;
+use hyper::{Client, Url};
+
+use error::{Error, Result};
+use forecast::Forecast;
+use locale::Locale;
+
+pub mod wwo;
+pub mod open_meteo;
+pub mod owm;
+
+pub use self::wwo::WorldWeatherOnline;
+pub use self::open_meteo::OpenMeteo;
+pub use self::owm::OpenWeatherMap;
+
+/// Names accepted by `by_name`, the first one is the default.
+pub static BACKENDS: [&'static str; 3] = ["wwo", "open-meteo", "owm"];
+
+/// What to ask a backend for.
+#[derive(Clone, Debug)]
+pub struct Query {
+    /// Any of the kinds of `location::Location`.
+    pub location: String,
+    pub days: usize,
+    /// Language for provider side descriptions.
+    pub locale: Locale,
+}
+
+/// `Sync` so that several locations can be fetched at once, see `fetch_all`.
+pub trait WeatherBackend: Sync {
+    fn fetch(&self, query: &Query) -> Result<Forecast>;
+}
+
+/// Fetches every query on its own thread. The results are in the order of
+/// the queries, one failing doesn't stop the others.
+pub fn fetch_all(backend: &dyn WeatherBackend, queries: &[Query]) -> Vec<Result<Forecast>> {
+    thread::scope(|s| {
+        let handles = queries.iter()
+            .map(|query| s.spawn(move || backend.fetch(query)))
+            .collect::<Vec<_>>();
+        handles.into_iter()
+            .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// Creates a backend by name. `key` is the API key for services that need one.
+pub fn by_name(name: &str, key: Option<String>) -> Result<Box<dyn WeatherBackend>> {
+    match (name, key) {
+        ("wwo", Some(key)) => Ok(Box::new(WorldWeatherOnline::with_key(&key))),
+        ("wwo", None)      => Ok(Box::new(WorldWeatherOnline::new())),
+        ("open-meteo", _)  => Ok(Box::new(OpenMeteo::new())),
+        ("owm", Some(key)) => Ok(Box::new(OpenWeatherMap::new(&key))),
+        ("owm", None)      => Err(Error::Usage("the owm backend needs an API key".to_string())),
+        _                  => Err(Error::Usage(format!("unknown backend: {}", name))),
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url> {
+    Url::parse(url).map_err(|e| Error::Network(format!("{}: {}", url, e)))
+}
+
+/// GETs a JSON document. `provider_error` turns the service's own error
+/// payloads, which may come with any status, into an `Error`.
+fn get_json(url: Url, provider_error: &dyn Fn(&Json) -> Option<Error>) -> Result<Json> {
+    let client = Client::new();
+    let mut res = client.get(url).send().map_err(|e| Error::Network(e.to_string()))?;
+
+    let mut buf = String::with_capacity(65535);
+    res.read_to_string(&mut buf).map_err(|e| Error::Network(e.to_string()))?;
+
+    let json = Json::from_str(&buf);
+    if let Some(err) = json.as_ref().ok().and_then(provider_error) {
+        return Err(err);
+    }
+    if !res.status.is_success() {
+        return Err(Error::Http(res.status.to_u16()));
+    }
+    // the start of the body is enough to tell e.g. an HTML page
+    json.map_err(|e| {
+        let mut body = buf.chars().take(200).collect::<String>();
+        if body.len() < buf.len() {
+            body.push('…');
+        }
+        Error::Decode(format!("{} (HTTP {}): {:?}", e, res.status.to_u16(), body))
+    })
+}
+
+// Helpers for walking decoded JSON. Providers are sloppy about types,
+// WorldWeatherOnline for one sends every number as a string.
+
+fn field<'a>(j: &'a Json, key: &str) -> Result<&'a Json> {
+    j.find(key).ok_or_else(|| Error::Decode(format!("missing field `{}`", key)))
+}
+
+fn json_f64(j: &Json) -> Option<f64> {
+    match *j {
+        Json::String(ref s) => s.trim().parse().ok(),
+        _                   => j.as_f64(),
+    }
+}
+
+fn json_i32(j: &Json) -> Option<i32> {
+    json_f64(j).map(|v| v.round() as i32)
+}
+
+fn f64_field(j: &Json, key: &str) -> Result<f64> {
+    field(j, key).and_then(|v| json_f64(v).ok_or_else(|| Error::Decode(format!("field `{}` is not a number", key))))
+}
+
+fn i32_field(j: &Json, key: &str) -> Result<i32> {
+    f64_field(j, key).map(|v| v.round() as i32)
+}
+
+fn opt_i32_field(j: &Json, key: &str) -> Option<i32> {
+    j.find(key).and_then(json_i32)
+}
+
+fn str_field<'a>(j: &'a Json, key: &str) -> Result<&'a str> {
+    field(j, key).and_then(|v| v.as_string().ok_or_else(|| Error::Decode(format!("field `{}` is not a string", key))))
+}
+
+fn array_field<'a>(j: &'a Json, key: &str) -> Result<&'a Vec<Json>> {
+    field(j, key).and_then(|v| v.as_array().ok_or_else(|| Error::Decode(format!("field `{}` is not an array", key))))
+}
+
+/// Checks a "%Y-%m-%d" date, so the renderers can rely on it.
+fn check_date(date: &str) -> Result<String> {
+    match ::time::strptime(date, "%Y-%m-%d") {
+        Ok(_)  => Ok(date.to_string()),
+        Err(_) => Err(Error::Date(date.to_string())),
+    }
+}
+
+fn opt_f64_field(j: &Json, key: &str) -> Option<f64> {
+    j.find(key).and_then(json_f64)
+}
+
+/// A local stand-in for the providers' HTTP APIs, serving recorded fixtures.
+#[cfg(test)]
+pub mod testing {
+    use std::io::prelude::*;
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+    use hyper::status::StatusCode;
+
+    /// Serves `(status, body)` for every request whose path starts with the
+    /// given prefix, 404 for anything else. Returns the base url and a channel
+    /// receiving each requested path with its query string.
+    pub fn serve(routes: Vec<(&'static str, u16, String)>) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(s)  => s,
+                    Err(_) => continue,
+                };
+                let mut path = String::new();
+                {
+                    let mut reader = BufReader::new(&stream);
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
+                        if path.is_empty() {
+                            path = line.split_whitespace().nth(1).unwrap_or("").to_string();
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        line.clear();
+                    }
+                }
+                let _ = tx.send(path.clone());
+
+                let (status, body) = match routes.iter().find(|r| path.starts_with(r.0)) {
+                    Some(route) => (route.1, route.2.clone()),
+                    None        => (404, String::new()),
+                };
+                let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+                                        Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                               StatusCode::from_u16(status), body.len(), body);
+            }
+        });
+
+        (base, rx)
+    }
+
+    pub fn fixture(name: &str) -> String {
+        let mut buf = String::new();
+        ::std::fs::File::open(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name))
+            .unwrap()
+            .read_to_string(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    /// Two days of Open-Meteo data for Beijing, for rendering tests.
+    pub fn sample_forecast() -> ::forecast::Forecast {
+        use backend::{OpenMeteo, Query, WeatherBackend};
+        use locale::Locale;
+
+        let (base, _requests) = serve(vec![("/v1/forecast", 200, fixture("open-meteo/forecast.json"))]);
+        let backend = OpenMeteo::with_urls(&format!("{}/v1/forecast", base), &format!("{}/v1/search", base));
+        let query = Query { location: "39.9075,116.3972".to_string(), days: 2, locale: Locale::En };
+        let mut forecast = backend.fetch(&query).unwrap();
+        forecast.location = "Beijing, China".to_string();
+        forecast
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::serve;
+
+    #[test]
+    fn undecodable_bodies_are_cut_short() {
+        let page = format!("<html><body>{}</body></html>", "Service Unavailable ".repeat(500));
+        let (base, _requests) = serve(vec![("/", 200, page)]);
+        match get_json(parse_url(&base).unwrap(), &|_| None) {
+            Err(Error::Decode(msg)) => {
+                assert!(msg.contains("(HTTP 200): \"<html><body>Service Unavailable"), "{}", msg);
+                assert!(msg.ends_with("…\""), "{}", msg);
+                assert!(msg.chars().count() < 300, "{}", msg);
+            }
+            other                   => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,471 @@
+// Open-Meteo forecast API, https://open-meteo.com/ . Free and keyless.
+
+use rustc_serialize::json::Json;
+
+use astro;
+use error::{Error, Result};
+use forecast::{degrees_to_16_point, Astronomy, Condition, Forecast, Weather, WeatherCondition};
+use location::{self, Location};
+use super::{Query, WeatherBackend, get_json, parse_url, check_date, field, str_field, array_field,
+            json_f64, json_i32, opt_f64_field, opt_i32_field};
+
+static FORECAST_URL: &'static str = "http://api.open-meteo.com/v1/forecast";
+static GEOCODING_URL: &'static str = "http://geocoding-api.open-meteo.com/v1/search";
+
+static HOURLY: &'static str = "temperature_2m,apparent_temperature,precipitation,weathercode,\
+                               windspeed_10m,winddirection_10m,windgusts_10m,visibility,\
+                               relativehumidity_2m,cloudcover,precipitation_probability,\
+                               pressure_msl,dewpoint_2m";
+static DAILY: &'static str = "weathercode,temperature_2m_max,temperature_2m_min,sunrise,sunset,uv_index_max";
+
+pub struct OpenMeteo {
+    forecast_url: String,
+    geocoding_url: String,
+}
+
+impl OpenMeteo {
+    pub fn new() -> OpenMeteo {
+        OpenMeteo::with_urls(FORECAST_URL, GEOCODING_URL)
+    }
+
+    pub fn with_urls(forecast_url: &str, geocoding_url: &str) -> OpenMeteo {
+        OpenMeteo {
+            forecast_url: forecast_url.to_string(),
+            geocoding_url: geocoding_url.to_string(),
+        }
+    }
+
+    /// Resolves a location to (name, latitude, longitude). Coordinates are
+    /// taken as is, place names and postal codes go through the geocoding
+    /// API.
+    fn resolve(&self, location: &Location) -> Result<(String, f64, f64)> {
+        let (search, country) = match *location {
+            Location::Coordinates(lat, lon)            => return Ok((location.to_string(), lat, lon)),
+            Location::Name(ref name)                   => (name, None),
+            Location::Postal { ref code, ref country } => (code, Some(country)),
+            _                                          => return Err(location.unsupported("open-meteo")),
+        };
+
+        let mut url = parse_url(&self.geocoding_url)?;
+        url.query_pairs_mut()
+            .clear()
+            .append_pair("name", search)
+            .append_pair("count", "1")
+            .append_pair("format", "json");
+        if let Some(country) = country {
+            url.query_pairs_mut().append_pair("countryCode", country);
+        }
+
+        let json = get_json(url, &provider_error)?;
+        let place = match json.find("results").and_then(|r| r.as_array()).and_then(|r| r.first()) {
+            Some(place) => place,
+            None        => return Err(Error::LocationNotFound(location.to_string())),
+        };
+
+        let name = location::display_name(str_field(place, "name")?,
+                                          place.find("admin1").and_then(|r| r.as_string()),
+                                          place.find("country").and_then(|c| c.as_string()));
+        let lat = field(place, "latitude").ok().and_then(json_f64);
+        let lon = field(place, "longitude").ok().and_then(json_f64);
+        match (lat, lon) {
+            (Some(lat), Some(lon)) => Ok((name, lat, lon)),
+            _                      => Err(Error::Decode(format!("no coordinates for {}", location))),
+        }
+    }
+}
+
+impl Default for OpenMeteo {
+    fn default() -> OpenMeteo {
+        OpenMeteo::new()
+    }
+}
+
+impl WeatherBackend for OpenMeteo {
+    fn fetch(&self, query: &Query) -> Result<Forecast> {
+        let (name, lat, lon) = self.resolve(&Location::parse(&query.location)?)?;
+
+        let mut url = parse_url(&self.forecast_url)?;
+        url.query_pairs_mut()
+            .clear()
+            .append_pair("latitude", &lat.to_string())
+            .append_pair("longitude", &lon.to_string())
+            .append_pair("hourly", HOURLY)
+            .append_pair("daily", DAILY)
+            .append_pair("current_weather", "true")
+            .append_pair("timezone", "auto")
+            .append_pair("forecast_days", &query.days.to_string());
+
+        let json = get_json(url, &provider_error)?;
+        let mut forecast = decode(&json, name)?;
+        forecast.coordinates = Some((lat, lon));
+        astro::complete(&mut forecast);
+        Ok(forecast)
+    }
+}
+
+// {"error": true, "reason": "Cannot initialize WeatherVariable from invalid String value"}
+fn provider_error(json: &Json) -> Option<Error> {
+    match json.find("error").and_then(|e| e.as_boolean()) {
+        Some(true) => Some(Error::Provider(json.find("reason")
+                                               .and_then(|r| r.as_string())
+                                               .unwrap_or("unknown error")
+                                               .to_string())),
+        _          => None,
+    }
+}
+
+// Open-Meteo sends columns: {"time": [...], "temperature_2m": [...], ...}
+struct Columns<'a> {
+    json: &'a Json,
+    len: usize,
+}
+
+impl<'a> Columns<'a> {
+    fn new(json: &'a Json) -> Result<Columns<'a>> {
+        let len = array_field(json, "time")?.len();
+        Ok(Columns { json, len })
+    }
+
+    fn get(&self, key: &str, idx: usize) -> Option<&'a Json> {
+        self.json.find(key).and_then(|c| c.as_array()).and_then(|c| c.get(idx))
+    }
+
+    fn f64(&self, key: &str, idx: usize) -> Option<f64> {
+        self.get(key, idx).and_then(json_f64)
+    }
+
+    fn i32(&self, key: &str, idx: usize) -> Option<i32> {
+        self.get(key, idx).and_then(json_i32)
+    }
+
+    // a temperature shown as 0 °C would be wrong, not missing
+    fn required_i32(&self, key: &str, idx: usize) -> Result<i32> {
+        self.i32(key, idx).ok_or_else(|| Error::Decode(format!("no {} at {}", key, idx)))
+    }
+
+    fn str(&self, key: &str, idx: usize) -> Option<&'a str> {
+        self.get(key, idx).and_then(|v| v.as_string())
+    }
+}
+
+fn decode(json: &Json, location: String) -> Result<Forecast> {
+    let hourly = Columns::new(field(json, "hourly")?)?;
+    let daily = Columns::new(field(json, "daily")?)?;
+
+    let mut days = Vec::with_capacity(daily.len);
+    for d in 0..daily.len {
+        let date = check_date(daily.str("time", d).unwrap_or(""))?;
+        let hours = (0..hourly.len)
+            .filter(|&h| hourly.str("time", h).map(|t| t.starts_with(&date)).unwrap_or(false))
+            .map(|h| decode_hour(&hourly, h))
+            .collect::<Result<Vec<_>>>()?;
+
+        days.push(Weather {
+            date,
+            astronomy: Astronomy {
+                sunrise: daily.str("sunrise", d).and_then(clock_of),
+                sunset: daily.str("sunset", d).and_then(clock_of),
+                moonrise: None,
+                moonset: None,
+                moon_phase: None,
+                moon_illumination: None,
+            },
+            hourly: hours,
+            max_temp_c: daily.required_i32("temperature_2m_max", d)?,
+            min_temp_c: daily.required_i32("temperature_2m_min", d)?,
+            uv_index: daily.i32("uv_index_max", d),
+        });
+    }
+
+    // current_weather only has temperature, wind and code, fill in the
+    // rest from the matching hourly entry.
+    let now = field(json, "current_weather")?;
+    let now_time = str_field(now, "time")?;
+    let now_hour = (0..hourly.len)
+        .find(|&h| hourly.str("time", h).map(|t| t.get(..13) == now_time.get(..13)).unwrap_or(false))
+        .ok_or_else(|| Error::Decode("current time is not in hourly data".to_string()))?;
+
+    let mut current = decode_hour(&hourly, now_hour)?;
+    current.hour = None;
+    if let Some(temp) = opt_i32_field(now, "temperature") {
+        current.temp_c = temp;
+    }
+    if let Some(code) = opt_i32_field(now, "weathercode") {
+        let (condition, desc) = wmo_code_to_condition(code);
+        current.condition = condition;
+        current.desc = desc.to_string();
+    }
+    if let Some(speed) = opt_i32_field(now, "windspeed") {
+        current.wind_speed_kmph = speed;
+    }
+    if let Some(dir) = opt_f64_field(now, "winddirection") {
+        current.wind_dir = degrees_to_16_point(dir).to_string();
+    }
+
+    Ok(Forecast {
+        location,
+        coordinates: None,
+        utc_offset: opt_i32_field(json, "utc_offset_seconds"),
+        current,
+        days,
+    })
+}
+
+fn decode_hour(hourly: &Columns, h: usize) -> Result<WeatherCondition> {
+    let (condition, desc) = wmo_code_to_condition(hourly.i32("weathercode", h).unwrap_or(-1));
+    let temp_c = hourly.required_i32("temperature_2m", h)?;
+
+    Ok(WeatherCondition {
+        condition,
+        desc: desc.to_string(),
+        local_desc: None,
+        hour: hourly.str("time", h).and_then(clock_of).and_then(|t| t[..2].parse().ok()),
+        temp_c,
+        feels_like_c: hourly.i32("apparent_temperature", h).unwrap_or(temp_c),
+        humidity: hourly.required_i32("relativehumidity_2m", h)?,
+        cloud_cover: hourly.i32("cloudcover", h).unwrap_or(0),
+        precip_mm: hourly.f64("precipitation", h).unwrap_or(0.0) as f32,
+        chance_of_rain: hourly.i32("precipitation_probability", h),
+        visibility_km: (hourly.f64("visibility", h).unwrap_or(0.0) / 1000.0).round() as i32,
+        wind_dir: degrees_to_16_point(hourly.f64("winddirection_10m", h).unwrap_or(0.0)).to_string(),
+        wind_speed_kmph: hourly.i32("windspeed_10m", h).unwrap_or(0),
+        wind_gust_kmph: hourly.i32("windgusts_10m", h),
+        pressure_hpa: hourly.i32("pressure_msl", h),
+        dewpoint_c: hourly.i32("dewpoint_2m", h),
+    })
+}
+
+// "2015-10-18T06:31" => "06:31"
+fn clock_of(iso: &str) -> Option<String> {
+    iso.split('T').nth(1).and_then(|t| t.get(..5)).map(|t| t.to_string())
+}
+
+// https://open-meteo.com/en/docs , "WMO Weather interpretation codes"
+fn wmo_code_to_condition(code: i32) -> (Condition, &'static str) {
+    match code {
+        0  => (Condition::Sunny, "Clear sky"),
+        1  => (Condition::PartlyCloudy, "Mainly clear"),
+        2  => (Condition::PartlyCloudy, "Partly cloudy"),
+        3  => (Condition::Cloudy, "Overcast"),
+        45 => (Condition::Fog, "Fog"),
+        48 => (Condition::Fog, "Depositing rime fog"),
+        51 => (Condition::LightRain, "Light drizzle"),
+        53 => (Condition::LightRain, "Moderate drizzle"),
+        55 => (Condition::LightRain, "Dense drizzle"),
+        56 => (Condition::LightSleet, "Light freezing drizzle"),
+        57 => (Condition::LightSleet, "Dense freezing drizzle"),
+        61 => (Condition::LightRain, "Slight rain"),
+        63 => (Condition::LightRain, "Moderate rain"),
+        65 => (Condition::HeavyRain, "Heavy rain"),
+        66 => (Condition::LightSleet, "Light freezing rain"),
+        67 => (Condition::LightSleet, "Heavy freezing rain"),
+        71 => (Condition::LightSnow, "Slight snow fall"),
+        73 => (Condition::LightSnow, "Moderate snow fall"),
+        75 => (Condition::HeavySnow, "Heavy snow fall"),
+        77 => (Condition::LightSnow, "Snow grains"),
+        80 => (Condition::LightShowers, "Slight rain showers"),
+        81 => (Condition::HeavyShowers, "Moderate rain showers"),
+        82 => (Condition::HeavyShowers, "Violent rain showers"),
+        85 => (Condition::LightSnowShowers, "Slight snow showers"),
+        86 => (Condition::HeavySnowShowers, "Heavy snow showers"),
+        95 => (Condition::ThunderyShowers, "Thunderstorm"),
+        96 => (Condition::ThunderyHeavyRain, "Thunderstorm with slight hail"),
+        99 => (Condition::ThunderyHeavyRain, "Thunderstorm with heavy hail"),
+        _  => (Condition::Unknown, "Unknown"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::{fixture, serve};
+    use backend::{fetch_all, Query, WeatherBackend};
+    use forecast::Condition;
+    use locale::Locale;
+
+    fn query(location: &str) -> Query {
+        Query {
+            location: location.to_string(),
+            days: 2,
+            locale: Locale::En,
+        }
+    }
+
+    fn backend() -> (OpenMeteo, ::std::sync::mpsc::Receiver<String>) {
+        let (base, requests) = serve(vec![
+            ("/v1/forecast", 200, fixture("open-meteo/forecast.json")),
+            ("/v1/search", 200, fixture("open-meteo/geocoding.json")),
+        ]);
+        let backend = OpenMeteo::with_urls(&format!("{}/v1/forecast", base),
+                                           &format!("{}/v1/search", base));
+        (backend, requests)
+    }
+
+    #[test]
+    fn fetch_by_city_name() {
+        let (backend, requests) = backend();
+        let forecast = backend.fetch(&query("Beijing")).unwrap();
+
+        assert!(requests.recv().unwrap().starts_with("/v1/search?name=Beijing"));
+        let forecast_req = requests.recv().unwrap();
+        assert!(forecast_req.contains("latitude=39.9075"));
+        assert!(forecast_req.contains("forecast_days=2"));
+
+        assert_eq!(forecast.location, "Beijing, China");
+        assert_eq!(forecast.days.len(), 2);
+        assert_eq!(forecast.days[0].date, "2026-10-18");
+        assert_eq!(forecast.days[0].hourly.len(), 24);
+        assert_eq!(forecast.days[0].max_temp_c, 18);
+        assert_eq!(forecast.days[0].min_temp_c, 6);
+        assert_eq!(forecast.days[0].uv_index, Some(4));
+        assert_eq!(forecast.days[0].astronomy.sunrise, Some("06:31".to_string()));
+        assert_eq!(forecast.days[1].astronomy.sunset, Some("17:29".to_string()));
+        assert_eq!(forecast.days[0].astronomy.day_length(), Some(660));
+        assert_eq!(forecast.days[0].astronomy.moon_illumination, Some(47));
+        assert_eq!(forecast.utc_offset, Some(28800));
+    }
+
+    #[test]
+    fn fetch_by_coordinates_skips_geocoding() {
+        let (backend, requests) = backend();
+        let forecast = backend.fetch(&query("39.9,116.4")).unwrap();
+
+        assert!(requests.recv().unwrap().starts_with("/v1/forecast?latitude=39.9&longitude=116.4"));
+        assert_eq!(forecast.location, "39.9,116.4");
+    }
+
+    #[test]
+    fn fetch_by_postal_code() {
+        let (backend, requests) = backend();
+        backend.fetch(&query("100006,cn")).unwrap();
+        assert!(requests.recv().unwrap().starts_with("/v1/search?name=100006&count=1&format=json&countryCode=CN"));
+
+        match backend.fetch(&query("8.8.8.8")) {
+            Err(Error::Usage(msg)) => assert_eq!(msg, "the open-meteo backend can't look up IP addresses"),
+            other                  => panic!("{:?}", other.map(|f| f.location)),
+        }
+        match backend.fetch(&query("icao:ZBAA")) {
+            Err(Error::Usage(msg)) => assert_eq!(msg, "the open-meteo backend can't look up ICAO airport codes"),
+            other                  => panic!("{:?}", other.map(|f| f.location)),
+        }
+    }
+
+    #[test]
+    fn names_in_capitals_are_places() {
+        let (backend, requests) = backend();
+        for name in &["OSLO", "ROME"] {
+            assert!(backend.fetch(&query(name)).is_ok());
+            assert!(requests.recv().unwrap().starts_with(&format!("/v1/search?name={}&", name)));
+            requests.recv().unwrap();
+        }
+    }
+
+    #[test]
+    fn fetch_all_keeps_the_order() {
+        // no geocoding, city names fail
+        let (base, requests) = serve(vec![("/v1/forecast", 200, fixture("open-meteo/forecast.json"))]);
+        let backend = OpenMeteo::with_urls(&format!("{}/v1/forecast", base), &format!("{}/v1/search", base));
+        let queries = vec![query("39.9,116.4"), query("Atlantis"), query("52.5,13.4")];
+        let results = fetch_all(&backend, &queries);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().location, "39.9,116.4");
+        assert_eq!(results[1].as_ref().unwrap_err().exit_code(), 7);
+        assert_eq!(results[2].as_ref().unwrap().location, "52.5,13.4");
+        assert_eq!(requests.iter().take(3).count(), 3);
+    }
+
+    #[test]
+    fn hourly_entries() {
+        let (backend, _requests) = backend();
+        let forecast = backend.fetch(&query("Beijing")).unwrap();
+
+        let noon = &forecast.days[1].hourly[12];
+        assert_eq!(noon.hour, Some(12));
+        assert_eq!(noon.condition, Condition::LightRain);
+        assert_eq!(noon.desc, "Moderate rain");
+        assert_eq!(noon.chance_of_rain, Some(52));
+
+        let foggy = &forecast.days[1].hourly[0];
+        assert_eq!(foggy.condition, Condition::Fog);
+        assert_eq!(foggy.visibility_km, 1);
+        assert_eq!(forecast.days[0].hourly[0].visibility_km, 24);
+    }
+
+    #[test]
+    fn current_condition_merges_hourly_data() {
+        let (backend, _requests) = backend();
+        let current = backend.fetch(&query("Beijing")).unwrap().current;
+
+        assert_eq!(current.hour, None);
+        assert_eq!(current.temp_c, 14);
+        assert_eq!(current.condition, Condition::PartlyCloudy);
+        assert_eq!(current.wind_speed_kmph, 12);
+        assert_eq!(current.wind_dir, "WSW");
+        assert_eq!(current.humidity, 60);
+    }
+
+    #[test]
+    fn unknown_city() {
+        let (base, _requests) = serve(vec![
+            ("/v1/search", 200, r#"{"generationtime_ms":0.3}"#.to_string()),
+        ]);
+        let backend = OpenMeteo::with_urls(&format!("{}/v1/forecast", base),
+                                           &format!("{}/v1/search", base));
+        match backend.fetch(&query("Nowhere")) {
+            Err(Error::LocationNotFound(loc)) => assert_eq!(loc, "Nowhere"),
+            other                             => panic!("{:?}", other.map(|f| f.location)),
+        }
+    }
+
+    #[test]
+    fn service_errors() {
+        let (base, _requests) = serve(vec![
+            ("/v1/forecast", 400, r#"{"error":true,"reason":"Parameter 'forecast_days' is out of range"}"#.to_string()),
+            ("/v1/search", 502, "<html>Bad Gateway</html>".to_string()),
+        ]);
+        let backend = OpenMeteo::with_urls(&format!("{}/v1/forecast", base),
+                                           &format!("{}/v1/search", base));
+        match backend.fetch(&query("1,2")) {
+            Err(Error::Provider(reason)) => assert!(reason.contains("forecast_days")),
+            other                        => panic!("{:?}", other.map(|f| f.location)),
+        }
+        match backend.fetch(&query("Beijing")) {
+            Err(Error::Http(502)) => (),
+            other                 => panic!("{:?}", other.map(|f| f.location)),
+        }
+    }
+
+    #[test]
+    fn missing_values() {
+        let (base, _requests) = serve(vec![("/v1/forecast", 200, fixture("open-meteo/forecast_nulls.json"))]);
+        let backend = OpenMeteo::with_urls(&format!("{}/v1/forecast", base),
+                                           &format!("{}/v1/search", base));
+        match backend.fetch(&query("39.9,116.4")) {
+            Err(e @ Error::Decode(_)) => assert_eq!(e.exit_code(), 8),
+            other                     => panic!("{:?}", other.map(|f| f.location)),
+        }
+
+        // each on its own, in the first hour or day
+        let forecast = fixture("open-meteo/forecast.json");
+        for key in &["temperature_2m", "relativehumidity_2m", "temperature_2m_max", "temperature_2m_min"] {
+            let column = format!("\"{}\":[", key);
+            let start = forecast.find(&column).unwrap() + column.len();
+            let end = start + forecast[start..].find(',').unwrap();
+            let json = Json::from_str(&format!("{}null{}", &forecast[..start], &forecast[end..])).unwrap();
+            match decode(&json, "Beijing".to_string()) {
+                Err(Error::Decode(msg)) => assert_eq!(msg, format!("no {} at 0", key)),
+                other                   => panic!("{}: {:?}", key, other.map(|f| f.location)),
+            }
+        }
+    }
+
+    #[test]
+    fn wind_directions() {
+        use forecast::degrees_to_16_point;
+        assert_eq!(degrees_to_16_point(0.0), "N");
+        assert_eq!(degrees_to_16_point(359.0), "N");
+        assert_eq!(degrees_to_16_point(247.0), "WSW");
+        assert_eq!(degrees_to_16_point(90.0), "E");
+    }
+}
@@ -0,0 +1,442 @@
+// OpenWeatherMap One Call API, https://openweathermap.org/api/one-call-3
+
+use rustc_serialize::json::Json;
+use time::{self, Timespec, Tm};
+
+use astro;
+use error::{Error, Result};
+use forecast::{degrees_to_16_point, Astronomy, Condition, Forecast, Weather, WeatherCondition};
+use locale::Locale;
+use location::{self, Location};
+use super::{Query, WeatherBackend, get_json, parse_url, field, str_field, array_field,
+            f64_field, i32_field, json_f64, opt_f64_field, opt_i32_field};
+
+static ONECALL_URL: &'static str = "http://api.openweathermap.org/data/3.0/onecall";
+static GEOCODING_URL: &'static str = "http://api.openweathermap.org/geo/1.0/direct";
+
+pub struct OpenWeatherMap {
+    onecall_url: String,
+    geocoding_url: String,
+    key: String,
+}
+
+impl OpenWeatherMap {
+    pub fn new(key: &str) -> OpenWeatherMap {
+        OpenWeatherMap::with_urls(key, ONECALL_URL, GEOCODING_URL)
+    }
+
+    pub fn with_urls(key: &str, onecall_url: &str, geocoding_url: &str) -> OpenWeatherMap {
+        OpenWeatherMap {
+            onecall_url: onecall_url.to_string(),
+            geocoding_url: geocoding_url.to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    /// Resolves a location to (name, latitude, longitude). Place names go
+    /// through direct geocoding, postal codes through the zip endpoint next
+    /// to it.
+    fn resolve(&self, location: &Location) -> Result<(String, f64, f64)> {
+        let query = location.to_string();
+        let mut url = parse_url(&self.geocoding_url)?;
+        match *location {
+            Location::Coordinates(lat, lon) => return Ok((query, lat, lon)),
+            Location::Name(ref name)        => {
+                url.query_pairs_mut()
+                    .clear()
+                    .append_pair("q", name)
+                    .append_pair("limit", "1");
+            }
+            Location::Postal { .. }         => {
+                url = url.join("zip").map_err(|e| Error::Network(format!("{}: {}", self.geocoding_url, e)))?;
+                url.query_pairs_mut()
+                    .clear()
+                    .append_pair("zip", &query);
+            }
+            _                               => return Err(location.unsupported("openweathermap")),
+        }
+        url.query_pairs_mut().append_pair("appid", &self.key);
+
+        let json = get_json(url, &|json| provider_error(json, &query))?;
+        // direct geocoding has a list of places, zip a single one
+        let place = match json.as_array() {
+            Some(places) => match places.first() {
+                Some(place) => place,
+                None        => return Err(Error::LocationNotFound(query)),
+            },
+            None         => &json,
+        };
+
+        let name = location::display_name(str_field(place, "name")?,
+                                          place.find("state").and_then(|s| s.as_string()),
+                                          place.find("country").and_then(|c| c.as_string()));
+        Ok((name, f64_field(place, "lat")?, f64_field(place, "lon")?))
+    }
+}
+
+impl WeatherBackend for OpenWeatherMap {
+    fn fetch(&self, query: &Query) -> Result<Forecast> {
+        let (name, lat, lon) = self.resolve(&Location::parse(&query.location)?)?;
+
+        let mut url = parse_url(&self.onecall_url)?;
+        url.query_pairs_mut()
+            .clear()
+            .append_pair("lat", &lat.to_string())
+            .append_pair("lon", &lon.to_string())
+            .append_pair("exclude", "minutely,alerts")
+            .append_pair("units", "metric")
+            .append_pair("lang", lang_code(query.locale))
+            .append_pair("appid", &self.key);
+
+        let json = get_json(url, &|json| provider_error(json, &query.location))?;
+        let mut forecast = decode(&json, name, query.locale)?;
+        astro::complete(&mut forecast);
+        Ok(forecast)
+    }
+}
+
+// {"cod": 401, "message": "Invalid API key. Please see https://openweathermap.org/faq#error401 for more info."}
+// {"cod": "404", "message": "city not found"}
+fn provider_error(json: &Json, location: &str) -> Option<Error> {
+    let msg = json.find("message").and_then(|m| m.as_string())?;
+    match opt_i32_field(json, "cod") {
+        Some(404)                 => Some(Error::LocationNotFound(location.to_string())),
+        Some(cod) if cod >= 400   => Some(Error::Provider(msg.to_string())),
+        _                         => None,
+    }
+}
+
+fn lang_code(locale: Locale) -> &'static str {
+    match locale {
+        Locale::En   => "en",
+        Locale::ZhCn => "zh_cn",
+        Locale::ZhTw => "zh_tw",
+        Locale::Ja   => "ja",
+        Locale::De   => "de",
+        Locale::Fr   => "fr",
+        Locale::Es   => "es",
+        Locale::Ru   => "ru",
+    }
+}
+
+fn decode(json: &Json, location: String, locale: Locale) -> Result<Forecast> {
+    let offset = i32_field(json, "timezone_offset").unwrap_or(0) as i64;
+
+    let mut hourly = Vec::new();
+    for h in array_field(json, "hourly")?.iter() {
+        let local = local_time(i64_field(h, "dt")?, offset);
+        let mut cond = decode_condition(h, i32_field(h, "temp")?, i32_field(h, "feels_like")?, locale)?;
+        cond.hour = Some(local.tm_hour as u32);
+        hourly.push((date_of(&local), cond));
+    }
+
+    let mut days = Vec::new();
+    for d in array_field(json, "daily")?.iter() {
+        let date = date_of(&local_time(i64_field(d, "dt")?, offset));
+        let temp = field(d, "temp")?;
+        let feels_like = field(d, "feels_like")?;
+
+        let mut hours: Vec<WeatherCondition> = hourly.iter()
+            .filter(|&(day, _)| *day == date)
+            .map(|(_, cond)| cond.clone())
+            .collect();
+
+        // hourly data only covers the next 48 hours, later days get one
+        // entry per part of the day from the daily temperatures
+        if hours.is_empty() {
+            for &(hour, part) in [(9, "morn"), (12, "day"), (18, "eve"), (21, "night")].iter() {
+                let mut cond = decode_condition(d, i32_field(temp, part)?, i32_field(feels_like, part)?, locale)?;
+                cond.hour = Some(hour);
+                // daily rain is the total for the whole day
+                cond.precip_mm /= 4.0;
+                hours.push(cond);
+            }
+        }
+
+        days.push(Weather {
+            astronomy: Astronomy {
+                sunrise: clock_field(d, "sunrise", offset),
+                sunset: clock_field(d, "sunset", offset),
+                moonrise: clock_field(d, "moonrise", offset),
+                moonset: clock_field(d, "moonset", offset),
+                // 0 and 1 are new moon, 0.5 full moon
+                moon_phase: opt_f64_field(d, "moon_phase"),
+                moon_illumination: None,
+            },
+            date,
+            hourly: hours,
+            max_temp_c: i32_field(temp, "max")?,
+            min_temp_c: i32_field(temp, "min")?,
+            uv_index: opt_i32_field(d, "uvi"),
+        });
+    }
+
+    let now = field(json, "current")?;
+    let current = decode_condition(now, i32_field(now, "temp")?, i32_field(now, "feels_like")?, locale)?;
+
+    Ok(Forecast {
+        location,
+        coordinates: opt_f64_field(json, "lat").and_then(|lat| opt_f64_field(json, "lon").map(|lon| (lat, lon))),
+        utc_offset: Some(offset as i32),
+        current,
+        days,
+    })
+}
+
+/// Everything but the temperatures, which are numbers in current and
+/// hourly blocks but objects in daily ones.
+fn decode_condition(c: &Json, temp_c: i32, feels_like_c: i32, locale: Locale) -> Result<WeatherCondition> {
+    let weather = match array_field(c, "weather")?.first() {
+        Some(w) => w,
+        None    => return Err(Error::Decode("missing weather".to_string())),
+    };
+    let description = str_field(weather, "description")?.to_string();
+    let (desc, local_desc) = if locale == Locale::En {
+        (description, None)
+    } else {
+        (str_field(weather, "main")?.to_string(), Some(description))
+    };
+
+    // daily blocks have rain/snow in mm, the others {"1h": mm}
+    let precip = |key: &str| match c.find(key) {
+        Some(v @ &Json::Object(_)) => v.find("1h").and_then(json_f64).unwrap_or(0.0),
+        Some(v)                    => json_f64(v).unwrap_or(0.0),
+        None                       => 0.0,
+    };
+
+    Ok(WeatherCondition {
+        condition: id_to_condition(i32_field(weather, "id")?),
+        desc,
+        local_desc,
+        hour: None,
+        temp_c,
+        feels_like_c,
+        humidity: i32_field(c, "humidity")?,
+        cloud_cover: i32_field(c, "clouds")?,
+        precip_mm: (precip("rain") + precip("snow")) as f32,
+        chance_of_rain: opt_f64_field(c, "pop").map(|p| (p * 100.0).round() as i32),
+        // daily blocks have no visibility
+        visibility_km: opt_f64_field(c, "visibility").map(|v| (v / 1000.0).round() as i32).unwrap_or(10),
+        wind_dir: degrees_to_16_point(f64_field(c, "wind_deg")?).to_string(),
+        wind_speed_kmph: mps_to_kmph(f64_field(c, "wind_speed")?),
+        wind_gust_kmph: opt_f64_field(c, "wind_gust").map(mps_to_kmph),
+        pressure_hpa: opt_i32_field(c, "pressure"),
+        dewpoint_c: opt_i32_field(c, "dew_point"),
+    })
+}
+
+fn mps_to_kmph(mps: f64) -> i32 {
+    (mps * 3.6).round() as i32
+}
+
+fn i64_field(j: &Json, key: &str) -> Result<i64> {
+    f64_field(j, key).map(|v| v as i64)
+}
+
+fn local_time(unix: i64, offset: i64) -> Tm {
+    time::at_utc(Timespec::new(unix + offset, 0))
+}
+
+fn date_of(tm: &Tm) -> String {
+    format!("{:04}-{:02}-{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday)
+}
+
+// 0 means the event doesn't happen that day, e.g. no moonrise
+fn clock_field(j: &Json, key: &str, offset: i64) -> Option<String> {
+    match i64_field(j, key) {
+        Ok(0) | Err(_) => None,
+        Ok(unix)       => {
+            let tm = local_time(unix, offset);
+            Some(format!("{:02}:{:02}", tm.tm_hour, tm.tm_min))
+        }
+    }
+}
+
+// https://openweathermap.org/weather-conditions
+fn id_to_condition(id: i32) -> Condition {
+    match id {
+        202 | 212 | 221 | 232 => Condition::ThunderyHeavyRain,
+        200..=299             => Condition::ThunderyShowers,
+        302 | 312 | 314       => Condition::HeavyShowers,
+        313 | 321             => Condition::LightShowers,
+        300..=399             => Condition::LightRain,
+        500 | 501             => Condition::LightRain,
+        502..=504             => Condition::HeavyRain,
+        511                   => Condition::LightSleet,
+        520                   => Condition::LightShowers,
+        521..=531             => Condition::HeavyShowers,
+        600 | 601             => Condition::LightSnow,
+        602                   => Condition::HeavySnow,
+        611..=616             => Condition::LightSleet,
+        620 | 621             => Condition::LightSnowShowers,
+        622                   => Condition::HeavySnowShowers,
+        700..=799             => Condition::Fog,
+        800                   => Condition::Sunny,
+        801 | 802             => Condition::PartlyCloudy,
+        803                   => Condition::Cloudy,
+        804                   => Condition::VeryCloudy,
+        _                     => Condition::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::{fixture, serve};
+    use backend::{Query, WeatherBackend};
+    use forecast::Condition;
+    use locale::Locale;
+
+    fn query(location: &str, locale: Locale) -> Query {
+        Query {
+            location: location.to_string(),
+            days: 4,
+            locale,
+        }
+    }
+
+    fn backend() -> (OpenWeatherMap, ::std::sync::mpsc::Receiver<String>) {
+        let (base, requests) = serve(vec![
+            ("/data/3.0/onecall", 200, fixture("owm/onecall.json")),
+            ("/geo/1.0/direct", 200, fixture("owm/geocoding.json")),
+            ("/geo/1.0/zip", 200, fixture("owm/zip.json")),
+        ]);
+        let backend = OpenWeatherMap::with_urls("secret",
+                                                &format!("{}/data/3.0/onecall", base),
+                                                &format!("{}/geo/1.0/direct", base));
+        (backend, requests)
+    }
+
+    #[test]
+    fn fetch_by_city_name() {
+        let (backend, requests) = backend();
+        let forecast = backend.fetch(&query("Beijing", Locale::En)).unwrap();
+
+        assert!(requests.recv().unwrap().starts_with("/geo/1.0/direct?q=Beijing&limit=1&appid=secret"));
+        let onecall = requests.recv().unwrap();
+        assert!(onecall.contains("lat=39.9057136"));
+        assert!(onecall.contains("units=metric"));
+
+        assert_eq!(forecast.location, "Beijing, CN");
+        assert_eq!(forecast.days.len(), 4);
+        assert_eq!(forecast.days[0].date, "2026-10-18");
+        assert_eq!(forecast.days[0].max_temp_c, 18);
+        assert_eq!(forecast.days[0].min_temp_c, 6);
+        assert_eq!(forecast.days[0].astronomy.sunrise, Some("06:31".to_string()));
+        assert_eq!(forecast.days[2].astronomy.moonrise, None);
+        // the provider's phase, the illumination computed from it
+        assert_eq!(forecast.days[0].astronomy.moon_phase, Some(0.25));
+        assert_eq!(forecast.days[0].astronomy.moon_illumination, Some(50));
+        assert_eq!(forecast.coordinates, Some((39.9075, 116.3972)));
+        assert_eq!(forecast.utc_offset, Some(28800));
+    }
+
+    #[test]
+    fn fetch_by_postal_code() {
+        let (backend, requests) = backend();
+        let forecast = backend.fetch(&query("100006, cn", Locale::En)).unwrap();
+
+        assert!(requests.recv().unwrap().starts_with("/geo/1.0/zip?zip=100006%2CCN&appid=secret"));
+        assert!(requests.recv().unwrap().contains("lat=39.9165"));
+        assert_eq!(forecast.location, "Dongcheng, CN");
+
+        match backend.fetch(&query("iata:PEK", Locale::En)) {
+            Err(Error::Usage(msg)) => assert_eq!(msg, "the openweathermap backend can't look up IATA airport codes"),
+            other                  => panic!("{:?}", other.map(|f| f.location)),
+        }
+        match backend.fetch(&query("icao:ZBAA", Locale::En)) {
+            Err(Error::Usage(msg)) => assert_eq!(msg, "the openweathermap backend can't look up ICAO airport codes"),
+            other                  => panic!("{:?}", other.map(|f| f.location)),
+        }
+    }
+
+    #[test]
+    fn names_in_capitals_are_places() {
+        let (backend, requests) = backend();
+        for name in &["OSLO", "ROME"] {
+            assert!(backend.fetch(&query(name, Locale::En)).is_ok());
+            assert!(requests.recv().unwrap().starts_with(&format!("/geo/1.0/direct?q={}&", name)));
+            requests.recv().unwrap();
+        }
+    }
+
+    #[test]
+    fn hourly_entries_are_split_by_local_date() {
+        let (backend, _requests) = backend();
+        let days = backend.fetch(&query("39.9,116.4", Locale::En)).unwrap().days;
+
+        // 48 hours from 10:00 on the first day
+        assert_eq!(days[0].hourly.len(), 14);
+        assert_eq!(days[0].hourly[0].hour, Some(10));
+        assert_eq!(days[1].hourly.len(), 24);
+        assert_eq!(days[2].hourly.len(), 10);
+
+        let rain = &days[1].hourly[0];
+        assert_eq!(rain.condition, Condition::LightRain);
+        assert_eq!(rain.precip_mm, 0.42);
+        assert_eq!(rain.chance_of_rain, Some(29));
+        let fog = &days[1].hourly[6];
+        assert_eq!(fog.condition, Condition::Fog);
+        assert_eq!(fog.visibility_km, 1);
+    }
+
+    #[test]
+    fn days_without_hourly_data_use_daily_parts() {
+        let (backend, _requests) = backend();
+        let day = &backend.fetch(&query("39.9,116.4", Locale::En)).unwrap().days[3];
+
+        let hours: Vec<_> = day.hourly.iter().map(|h| h.hour.unwrap()).collect();
+        assert_eq!(hours, vec![9, 12, 18, 21]);
+        assert_eq!(day.hourly[0].temp_c, 11);
+        assert_eq!(day.hourly[3].feels_like_c, 10);
+        assert_eq!(day.hourly[1].condition, Condition::Sunny);
+        assert_eq!(day.hourly[2].precip_mm, 0.575);
+    }
+
+    #[test]
+    fn current_condition() {
+        let (backend, _requests) = backend();
+        let current = backend.fetch(&query("39.9,116.4", Locale::ZhCn)).unwrap().current;
+
+        assert_eq!(current.condition, Condition::Sunny);
+        assert_eq!(current.desc, "Clear");
+        assert_eq!(current.local_desc, Some("clear sky".to_string()));
+        assert_eq!(current.temp_c, 14);
+        assert_eq!(current.wind_speed_kmph, 11);
+        assert_eq!(current.wind_dir, "WSW");
+        assert_eq!(current.wind_gust_kmph, None);
+        assert_eq!(current.pressure_hpa, Some(1016));
+        assert_eq!(current.dewpoint_c, Some(3));
+    }
+
+    #[test]
+    fn condition_ids() {
+        assert_eq!(id_to_condition(200), Condition::ThunderyShowers);
+        assert_eq!(id_to_condition(202), Condition::ThunderyHeavyRain);
+        assert_eq!(id_to_condition(310), Condition::LightRain);
+        assert_eq!(id_to_condition(522), Condition::HeavyShowers);
+        assert_eq!(id_to_condition(615), Condition::LightSleet);
+        assert_eq!(id_to_condition(741), Condition::Fog);
+        assert_eq!(id_to_condition(804), Condition::VeryCloudy);
+        assert_eq!(id_to_condition(900), Condition::Unknown);
+    }
+
+    #[test]
+    fn service_errors() {
+        let (base, _requests) = serve(vec![
+            ("/data/3.0/onecall", 401, r#"{"cod":401,"message":"Invalid API key."}"#.to_string()),
+            ("/geo/1.0/direct", 200, "[]".to_string()),
+        ]);
+        let backend = OpenWeatherMap::with_urls("wrong",
+                                                &format!("{}/data/3.0/onecall", base),
+                                                &format!("{}/geo/1.0/direct", base));
+        match backend.fetch(&query("1,2", Locale::En)) {
+            Err(Error::Provider(msg)) => assert_eq!(msg, "Invalid API key."),
+            other                     => panic!("{:?}", other.map(|f| f.location)),
+        }
+        match backend.fetch(&query("Nowhere", Locale::En)) {
+            Err(Error::LocationNotFound(loc)) => assert_eq!(loc, "Nowhere"),
+            other                             => panic!("{:?}", other.map(|f| f.location)),
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// WorldWeatherOnline premium/free v2 API.
+
+use rustc_serialize::json::Json;
+
+use astro;
+use error::{Error, Result};
+use forecast::{Astronomy, Condition, Forecast, Weather, WeatherCondition};
+use locale::Locale;
+use location::{self, Location};
+use super::{Query, WeatherBackend, get_json, parse_url, check_date, field, str_field, array_field,
+            i32_field, opt_i32_field, f64_field};
+
+static BASE_URL: &'static str = "http://api.worldweatheronline.com/free/v2/weather.ashx";
+static KEY: &'static str = "a444bbde1001764c4634bc7079a7c";
+
+pub struct WorldWeatherOnline {
+    base_url: String,
+    key: String,
+}
+
+impl WorldWeatherOnline {
+    pub fn new() -> WorldWeatherOnline {
+        WorldWeatherOnline::with_key(KEY)
+    }
+
+    pub fn with_key(key: &str) -> WorldWeatherOnline {
+        WorldWeatherOnline {
+            base_url: BASE_URL.to_string(),
+            key: key.to_string(),
+        }
+    }
+}
+
+impl Default for WorldWeatherOnline {
+    fn default() -> WorldWeatherOnline {
+        WorldWeatherOnline::new()
+    }
+}
+
+impl WeatherBackend for WorldWeatherOnline {
+    fn fetch(&self, query: &Query) -> Result<Forecast> {
+        // the service reads every kind but ICAO codes from `q`, IATA codes
+        // bare
+        let location = match Location::parse(&query.location)? {
+            Location::Iata(code)         => code,
+            location @ Location::Icao(_) => return Err(location.unsupported("wwo")),
+            location                     => location.to_string(),
+        };
+
+        let mut url = parse_url(&self.base_url)?;
+        url.query_pairs_mut()
+            .clear()
+            .append_pair("q", &location)
+            .append_pair("key", &self.key)
+            .append_pair("num_of_days", &query.days.to_string())
+            .append_pair("lang", lang_code(query.locale))
+            .append_pair("includelocation", "yes")
+            .append_pair("format", "json");
+
+        let json = get_json(url, &|json| provider_error(json, &query.location))?;
+        let mut forecast = decode(&json, lang_code(query.locale))?;
+        astro::complete(&mut forecast);
+        Ok(forecast)
+    }
+}
+
+// {"data": {"error": [{"msg": "Unable to find any matching weather location to the query submitted!"}]}}
+fn provider_error(json: &Json, location: &str) -> Option<Error> {
+    let msg = json.find_path(&["data", "error"])
+        .and_then(|e| e.as_array())
+        .and_then(|e| e.first())
+        .and_then(|e| e.find("msg"))
+        .and_then(|m| m.as_string())?;
+    if msg.contains("location") {
+        Some(Error::LocationNotFound(location.to_string()))
+    } else {
+        Some(Error::Provider(msg.to_string()))
+    }
+}
+
+fn lang_code(locale: Locale) -> &'static str {
+    match locale {
+        Locale::En   => "en",
+        Locale::ZhCn => "zh",
+        Locale::ZhTw => "zh_tw",
+        Locale::Ja   => "ja",
+        Locale::De   => "de",
+        Locale::Fr   => "fr",
+        Locale::Es   => "es",
+        Locale::Ru   => "ru",
+    }
+}
+
+fn decode(json: &Json, lang: &str) -> Result<Forecast> {
+    let data = field(json, "data")?;
+
+    let query = match array_field(data, "request")?.first() {
+        Some(req) => str_field(req, "query")?.to_string(),
+        None      => return Err(Error::Decode("missing request".to_string())),
+    };
+
+    // the place the weather is for, the query if it's missing
+    let area = data.find("nearest_area").and_then(|a| a.as_array()).and_then(|a| a.first());
+    let location = match area.and_then(|a| first_value(a, "areaName")) {
+        Some(name) => location::display_name(&name,
+                                             area.and_then(|a| first_value(a, "region")).as_ref().map(|r| &r[..]),
+                                             area.and_then(|a| first_value(a, "country")).as_ref().map(|c| &c[..])),
+        None       => query,
+    };
+    // "52.517", as strings
+    let coordinates = area.and_then(|a| Some((f64_field(a, "latitude").ok()?, f64_field(a, "longitude").ok()?)));
+
+    let current = match array_field(data, "current_condition")?.first() {
+        Some(cond) => decode_condition(cond, lang)?,
+        None       => return Err(Error::Decode("missing current_condition".to_string())),
+    };
+
+    let mut days = Vec::new();
+    for w in array_field(data, "weather")?.iter() {
+        days.push(decode_weather(w, lang)?);
+    }
+
+    Ok(Forecast {
+        location,
+        coordinates,
+        utc_offset: None,
+        current,
+        days,
+    })
+}
+
+fn decode_weather(w: &Json, lang: &str) -> Result<Weather> {
+    let astronomy = match array_field(w, "astronomy")?.first() {
+        Some(astro) => Astronomy {
+            sunrise: str_field(astro, "sunrise").ok().and_then(parse_clock),
+            sunset: str_field(astro, "sunset").ok().and_then(parse_clock),
+            moonrise: str_field(astro, "moonrise").ok().and_then(parse_clock),
+            moonset: str_field(astro, "moonset").ok().and_then(parse_clock),
+            moon_phase: None,
+            // "46", in %
+            moon_illumination: opt_i32_field(astro, "moon_illumination"),
+        },
+        None        => Astronomy::default(),
+    };
+
+    let mut hourly = Vec::new();
+    for h in array_field(w, "hourly")?.iter() {
+        hourly.push(decode_condition(h, lang)?);
+    }
+
+    Ok(Weather {
+        date: check_date(str_field(w, "date")?)?,
+        astronomy,
+        hourly,
+        max_temp_c: i32_field(w, "maxtempC")?,
+        min_temp_c: i32_field(w, "mintempC")?,
+        uv_index: opt_i32_field(w, "uvIndex"),
+    })
+}
+
+fn decode_condition(c: &Json, lang: &str) -> Result<WeatherCondition> {
+    // current_condition says temp_C, hourly entries say tempC
+    let temp_c = opt_i32_field(c, "tempC")
+        .or_else(|| opt_i32_field(c, "temp_C"))
+        .ok_or_else(|| Error::Decode("missing field `tempC`".to_string()))?;
+    // hourly time is "0", "300", ... "2100"
+    let hour = opt_i32_field(c, "time").map(|t| (t / 100) as u32);
+
+    Ok(WeatherCondition {
+        condition: code_to_condition(i32_field(c, "weatherCode")?),
+        desc: first_value(c, "weatherDesc").unwrap_or_default(),
+        // translations come as lang_zh, lang_de, ...
+        local_desc: first_value(c, &format!("lang_{}", lang)),
+        hour,
+        temp_c,
+        feels_like_c: i32_field(c, "FeelsLikeC")?,
+        humidity: i32_field(c, "humidity")?,
+        cloud_cover: i32_field(c, "cloudcover")?,
+        precip_mm: f64_field(c, "precipMM")? as f32,
+        chance_of_rain: opt_i32_field(c, "chanceofrain"),
+        visibility_km: i32_field(c, "visibility")?,
+        wind_dir: str_field(c, "winddir16Point")?.to_string(),
+        wind_speed_kmph: i32_field(c, "windspeedKmph")?,
+        wind_gust_kmph: opt_i32_field(c, "WindGustKmph"),
+        pressure_hpa: opt_i32_field(c, "pressure"),
+        // hourly entries only
+        dewpoint_c: opt_i32_field(c, "DewPointC"),
+    })
+}
+
+// [{"value": "..."}]
+fn first_value(c: &Json, key: &str) -> Option<String> {
+    c.find(key)
+        .and_then(|v| v.as_array())
+        .and_then(|arr| arr.first())
+        .and_then(|v| v.find("value"))
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string())
+}
+
+// "06:45 AM" => "06:45", "No moonrise" => None
+fn parse_clock(s: &str) -> Option<String> {
+    let mut parts = s.split_whitespace();
+    let hm = parts.next()?;
+    let ampm = parts.next()?;
+    let mut hm_parts = hm.split(':');
+    let h: u32 = hm_parts.next()?.parse().ok()?;
+    let m: u32 = hm_parts.next()?.parse().ok()?;
+    let h = match ampm {
+        "AM" => h % 12,
+        "PM" => h % 12 + 12,
+        _    => return None,
+    };
+    Some(format!("{:02}:{:02}", h, m))
+}
+
+fn code_to_condition(code: i32) -> Condition {
+    match code {
+        113 => Condition::Sunny,
+        116 => Condition::PartlyCloudy,
+        119 => Condition::Cloudy,
+        122 => Condition::VeryCloudy,
+        143 => Condition::Fog,
+        176 => Condition::LightShowers,
+        179 => Condition::LightSleetShowers,
+        182 => Condition::LightSleet,
+        185 => Condition::LightSleet,
+        200 => Condition::ThunderyShowers,
+        227 => Condition::LightSnow,
+        230 => Condition::HeavySnow,
+        248 => Condition::Fog,
+        260 => Condition::Fog,
+        263 => Condition::LightShowers,
+        266 => Condition::LightRain,
+        281 => Condition::LightSleet,
+        284 => Condition::LightSleet,
+        293 => Condition::LightRain,
+        296 => Condition::LightRain,
+        299 => Condition::HeavyShowers,
+        302 => Condition::HeavyRain,
+        305 => Condition::HeavyShowers,
+        308 => Condition::HeavyRain,
+        311 => Condition::LightSleet,
+        314 => Condition::LightSleet,
+        317 => Condition::LightSleet,
+        320 => Condition::LightSnow,
+        323 => Condition::LightSnowShowers,
+        326 => Condition::LightSnowShowers,
+        329 => Condition::HeavySnow,
+        332 => Condition::HeavySnow,
+        335 => Condition::HeavySnowShowers,
+        338 => Condition::HeavySnow,
+        350 => Condition::LightSleet,
+        353 => Condition::LightShowers,
+        356 => Condition::HeavyShowers,
+        359 => Condition::HeavyRain,
+        362 => Condition::LightSleetShowers,
+        365 => Condition::LightSleetShowers,
+        368 => Condition::LightSnowShowers,
+        371 => Condition::HeavySnowShowers,
+        374 => Condition::LightSleetShowers,
+        377 => Condition::LightSleet,
+        386 => Condition::ThunderyShowers,
+        389 => Condition::ThunderyHeavyRain,
+        392 => Condition::ThunderySnowShowers,
+        395 => Condition::HeavySnowShowers, // ThunderyHeavySnow
+        _   => Condition::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::serve;
+    use backend::{Query, WeatherBackend};
+
+    #[test]
+    fn error_payloads() {
+        let json = Json::from_str(r#"{"data":{"error":[{"msg":"Unable to find any matching weather location to the query submitted!"}]}}"#).unwrap();
+        match provider_error(&json, "Nowhere") {
+            Some(Error::LocationNotFound(loc)) => assert_eq!(loc, "Nowhere"),
+            other                              => panic!("{:?}", other),
+        }
+
+        let json = Json::from_str(r#"{"data":{"error":[{"msg":"API key has reached calls per day allowed limit."}]}}"#).unwrap();
+        match provider_error(&json, "Beijing") {
+            Some(Error::Provider(msg)) => assert!(msg.contains("limit")),
+            other                      => panic!("{:?}", other),
+        }
+
+        let json = Json::from_str(r#"{"data":{"request":[]}}"#).unwrap();
+        assert!(provider_error(&json, "Beijing").is_none());
+    }
+
+    #[test]
+    fn query_kinds() {
+        let not_found = r#"{"data":{"error":[{"msg":"Unable to find any matching weather location to the query submitted!"}]}}"#;
+        let (base, requests) = serve(vec![("/free/v2/weather.ashx", 200, not_found.to_string())]);
+        let backend = WorldWeatherOnline {
+            base_url: format!("{}/free/v2/weather.ashx", base),
+            key: "secret".to_string(),
+        };
+        let fetch = |location: &str| backend.fetch(&Query { location: location.to_string(), days: 1, locale: Locale::En });
+
+        // names in capitals are places, airport codes go without the prefix
+        for &(location, q) in &[("OSLO", "OSLO"), ("ROME", "ROME"), ("iata:fra", "FRA"), ("10115,de", "10115%2CDE")] {
+            match fetch(location) {
+                Err(Error::LocationNotFound(_)) => {}
+                other                           => panic!("{:?}", other.map(|f| f.location)),
+            }
+            assert!(requests.recv().unwrap().contains(&format!("q={}&", q)));
+        }
+        match fetch("icao:EDDF") {
+            Err(Error::Usage(msg)) => assert_eq!(msg, "the wwo backend can't look up ICAO airport codes"),
+            other                  => panic!("{:?}", other.map(|f| f.location)),
+        }
+    }
+
+    #[test]
+    fn nearest_area() {
+        let mut json = Json::from_str(r#"{"data":{
+            "request":[{"type":"Zipcode","query":"10115,DE"}],
+            "nearest_area":[{"areaName":[{"value":"Mitte"}],"region":[{"value":"Berlin"}],
+                             "country":[{"value":"Germany"}],"latitude":"52.532","longitude":"13.384"}],
+            "current_condition":[{"temp_C":"9","FeelsLikeC":"7","humidity":"76","cloudcover":"50",
+                                  "precipMM":"0.0","visibility":"10","winddir16Point":"WSW",
+                                  "windspeedKmph":"13","weatherCode":"116"}],
+            "weather":[]}}"#).unwrap();
+        let forecast = decode(&json, "en").unwrap();
+        assert_eq!(forecast.location, "Mitte, Berlin, Germany");
+        assert_eq!(forecast.coordinates, Some((52.532, 13.384)));
+
+        json.as_object_mut().unwrap().get_mut("data").unwrap().as_object_mut().unwrap().remove("nearest_area");
+        let forecast = decode(&json, "en").unwrap();
+        assert_eq!(forecast.location, "10115,DE");
+        assert_eq!(forecast.coordinates, None);
+    }
+}
@@ -0,0 +1,387 @@
+// Trend charts of the hourly data across the forecast days: a line chart of
+// the temperature and feels-like, bars of the precipitation and the chance of
+// rain. Each column is one hourly entry, or several when they don't fit.
+
+use std::env;
+use std::io::prelude::*;
+
+use color::{self, Color};
+use error::Result;
+use forecast::{Forecast, WeatherCondition};
+use locale::Text;
+use render::{self, HasTerminalDisplayLength, Settings};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChartStyle {
+    /// Braille dots for the lines, eighth blocks for the bars.
+    Unicode,
+    /// `*`, `.` and `#`, for consoles and fonts without those.
+    Ascii,
+}
+
+pub static CHART_STYLES: [&'static str; 2] = ["unicode", "ascii"];
+
+/// Rows of the temperature chart, four braille dots each.
+static TEMP_ROWS: usize = 8;
+static PRECIP_ROWS: usize = 4;
+static CHANCE_ROWS: usize = 2;
+
+/// Feels-like, where it doesn't share a cell with the temperature.
+static FEELS_LIKE: Color = Color::Indexed(245);
+
+impl ChartStyle {
+    pub fn from_name(name: &str) -> Option<ChartStyle> {
+        match name {
+            "unicode" => Some(ChartStyle::Unicode),
+            "ascii"   => Some(ChartStyle::Ascii),
+            _         => None,
+        }
+    }
+
+    /// ASCII when the locale's character set isn't UTF-8, e.g. LANG=C.
+    pub fn detect() -> ChartStyle {
+        let ctype = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|val| !val.is_empty());
+        match ctype {
+            Some(ref val) if !val.to_uppercase().replace('-', "").contains("UTF8") => ChartStyle::Ascii,
+            _                                                                   => ChartStyle::Unicode,
+        }
+    }
+}
+
+/// Like `render::print_forecast`, with the charts in place of the day tables.
+pub fn print_charts(w: &mut dyn Write, forecast: &Forecast, days: usize, style: ChartStyle,
+                    settings: &Settings) -> Result<()> {
+    render::print_current(w, forecast, settings)?;
+
+    let mut hours: Vec<&WeatherCondition> = Vec::new();
+    let mut dates = Vec::new();
+    for day in forecast.days.iter().take(days) {
+        dates.push((hours.len(), day.format_date(settings.locale)?));
+        hours.extend(day.hourly.iter());
+    }
+    if hours.is_empty() {
+        return Ok(());
+    }
+
+    let units = settings.units;
+    let temps: Vec<f64> = hours.iter().map(|h| units.temp(h.temp_c) as f64).collect();
+    let feels: Vec<f64> = hours.iter().map(|h| units.temp(h.feels_like_c) as f64).collect();
+    let celsius: Vec<f64> = hours.iter().map(|h| h.temp_c as f64).collect();
+    let lo = temps.iter().chain(feels.iter()).cloned().fold(f64::INFINITY, f64::min);
+    let hi = temps.iter().chain(feels.iter()).cloned().fold(f64::NEG_INFINITY, f64::max);
+    let max_precip = hours.iter().map(|h| h.precip_mm).fold(0.0, f32::max);
+
+    let labels = [format!("{} {}", hi, units.temp_unit()), format!("{} {}", lo, units.temp_unit()),
+                  units.format_precip(max_precip), units.format_precip(0.0),
+                  "100 %".to_string(), "0 %".to_string()];
+    let label_width = labels.iter().map(|l| l.len_on_term()).max().unwrap_or(0);
+    let per_col = hours.len().div_ceil(settings.width.saturating_sub(label_width + 2).max(10));
+    let chart = Chart { style, settings, label_width, per_col, cols: hours.len().div_ceil(per_col) };
+
+    let (temp_mark, feels_mark) = match style {
+        ChartStyle::Unicode => ("⠶", "⠆"),
+        ChartStyle::Ascii   => ("*", "."),
+    };
+    writeln!(w)?;
+    writeln!(w, "{} {}  {} {}", temp_mark, settings.locale.text(Text::Temperature),
+             chart.paint(FEELS_LIKE, feels_mark), settings.locale.text(Text::FeelsLike))?;
+    chart.print_lines(w, &temps, &feels, &celsius, (lo, hi), &labels[..2])?;
+    chart.print_axis(w, &dates)?;
+
+    // per column, the mean precipitation and the highest chance
+    let precip: Vec<f64> = hours.chunks(per_col)
+        .map(|c| c.iter().map(|h| h.precip_mm as f64).sum::<f64>() / c.len() as f64)
+        .collect();
+    let chance: Vec<f64> = hours.chunks(per_col)
+        .map(|c| c.iter().filter_map(|h| h.chance_of_rain).max().unwrap_or(0) as f64)
+        .collect();
+
+    writeln!(w)?;
+    writeln!(w, "{}", settings.locale.text(Text::Precipitation))?;
+    chart.print_bars(w, &precip, (max_precip as f64).max(0.1), PRECIP_ROWS, settings.theme.rain, &labels[2..4])?;
+    chart.print_axis(w, &[])?;
+    writeln!(w, "{}", settings.locale.text(Text::ChanceOfRain))?;
+    chart.print_bars(w, &chance, 100.0, CHANCE_ROWS, settings.theme.cloud, &labels[4..])?;
+    chart.print_axis(w, &dates)
+}
+
+struct Chart<'a> {
+    style: ChartStyle,
+    settings: &'a Settings,
+    label_width: usize,
+    /// Hourly entries per column.
+    per_col: usize,
+    cols: usize,
+}
+
+impl<'a> Chart<'a> {
+    fn paint(&self, c: Color, s: &str) -> String {
+        if self.settings.color { c.paint(s, self.settings.depth) } else { s.to_string() }
+    }
+
+    /// The label and axis in front of row `row` of `rows`: the highest value
+    /// on the first row, the lowest on the last.
+    fn prefix(&self, row: usize, rows: usize, labels: &[String]) -> String {
+        let (label, tick) = match row {
+            0                  => (labels[0].as_str(), true),
+            r if r == rows - 1 => (labels[1].as_str(), true),
+            _                  => ("", false),
+        };
+        let axis = match (self.style, tick) {
+            (ChartStyle::Unicode, true)  => "┤",
+            (ChartStyle::Unicode, false) => "│",
+            (ChartStyle::Ascii, true)    => "+",
+            (ChartStyle::Ascii, false)   => "|",
+        };
+        format!("{:>w$} {}", label, self.settings.frame(axis), w = self.label_width)
+    }
+
+    /// The x axis, with a tick and the date at the first entry of each day.
+    fn print_axis(&self, w: &mut dyn Write, dates: &[(usize, String)]) -> Result<()> {
+        let (corner, line, tick) = match self.style {
+            ChartStyle::Unicode => ("└", "─", "┬"),
+            ChartStyle::Ascii   => ("+", "-", "+"),
+        };
+        let mut axis: Vec<&str> = vec![line; self.cols];
+        for &(i, _) in dates {
+            axis[i / self.per_col] = tick;
+        }
+        writeln!(w, "{:w$} {}", "", self.settings.frame(&format!("{}{}", corner, axis.concat())),
+                 w = self.label_width)?;
+
+        if dates.is_empty() {
+            return Ok(());
+        }
+        let mut row = String::new();
+        for &(i, ref date) in dates {
+            let col = i / self.per_col;
+            let len = row.len_on_term();
+            if col > len || len == 0 {
+                row.push_str(&" ".repeat(col - len.min(col)));
+                row.push_str(date);
+            }
+        }
+        writeln!(w, "{:w$}  {}", "", row, w = self.label_width)?;
+        Ok(())
+    }
+
+    /// Temperature and feels-like lines in display units, `celsius` picks
+    /// the colours.
+    fn print_lines(&self, w: &mut dyn Write, temps: &[f64], feels: &[f64], celsius: &[f64],
+                   (lo, hi): (f64, f64), labels: &[String]) -> Result<()> {
+        let rows = TEMP_ROWS;
+        let (dots_x, dots_y) = match self.style {
+            ChartStyle::Unicode => (2, 4),
+            ChartStyle::Ascii   => (1, 1),
+        };
+        let temp_dots = plot(temps, self.cols * dots_x, rows * dots_y, self.per_col, (lo, hi));
+        let feels_dots = plot(feels, self.cols * dots_x, rows * dots_y, self.per_col, (lo, hi));
+
+        for row in 0..rows {
+            let mut line = self.prefix(row, rows, labels);
+            for col in 0..self.cols {
+                let temp = cell(&temp_dots, col, row, dots_x, dots_y);
+                let feel = cell(&feels_dots, col, row, dots_x, dots_y);
+                // the temperature fills both dot columns and wins a shared
+                // cell, feels-like only uses the left one, so that they can
+                // be told apart without colours
+                let glyph = match self.style {
+                    ChartStyle::Unicode if temp != 0 => braille(temp).to_string(),
+                    ChartStyle::Unicode              => braille(left_column(feel)).to_string(),
+                    ChartStyle::Ascii if temp != 0   => "*".to_string(),
+                    ChartStyle::Ascii                => ".".to_string(),
+                };
+                if temp != 0 {
+                    let c = sample(celsius, (col * self.per_col) as f64).round() as i32;
+                    line.push_str(&self.paint(color::scale_color(&self.settings.theme.temperature, c,
+                                                                 self.settings.depth), &glyph));
+                } else if feel != 0 {
+                    line.push_str(&self.paint(FEELS_LIKE, &glyph));
+                } else {
+                    line.push(' ');
+                }
+            }
+            writeln!(w, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+
+    /// One bar per column, `max` at full height.
+    fn print_bars(&self, w: &mut dyn Write, values: &[f64], max: f64, rows: usize, c: Color,
+                  labels: &[String]) -> Result<()> {
+        for row in 0..rows {
+            let mut line = self.prefix(row, rows, labels);
+            // eighths below the bottom of this row
+            let base = (rows - 1 - row) * 8;
+            for &v in values {
+                let eighths = (v / max * (rows * 8) as f64).round() as usize;
+                let fill = eighths.saturating_sub(base).min(8);
+                let glyph = match self.style {
+                    ChartStyle::Unicode if fill > 0 => BLOCKS[fill - 1],
+                    ChartStyle::Ascii if fill >= 4  => "#",
+                    _                               => " ",
+                };
+                if glyph == " " {
+                    line.push(' ');
+                } else {
+                    line.push_str(&self.paint(c, glyph));
+                }
+            }
+            writeln!(w, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+static BLOCKS: [&'static str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// One glyph per value, from lowest at `lo` to highest at `hi`.
+pub fn sparkline(values: &[f64], (lo, hi): (f64, f64), style: ChartStyle) -> Vec<&'static str> {
+    static ASCII: [&'static str; 5] = ["_", ".", "-", "~", "^"];
+    let levels: &[&'static str] = match style {
+        ChartStyle::Unicode => &BLOCKS,
+        ChartStyle::Ascii   => &ASCII,
+    };
+    let span = if hi > lo { hi - lo } else { 1.0 };
+    let top = levels.len() - 1;
+    values.iter()
+        .map(|v| levels[(((v - lo) / span * top as f64).round().max(0.0) as usize).min(top)])
+        .collect()
+}
+
+/// `series` at fractional index `pos`, interpolated.
+fn sample(series: &[f64], pos: f64) -> f64 {
+    let pos = pos.max(0.0).min((series.len() - 1) as f64);
+    let i = pos.floor() as usize;
+    match series.get(i + 1) {
+        Some(next) => series[i] + (next - series[i]) * (pos - i as f64),
+        None       => series[i],
+    }
+}
+
+/// A line through `series` on a `width` by `height` grid of dots, row 0 at
+/// the top. Consecutive dots are joined vertically so steep slopes stay
+/// connected.
+fn plot(series: &[f64], width: usize, height: usize, per_col: usize, (lo, hi): (f64, f64)) -> Vec<Vec<bool>> {
+    let mut grid = vec![vec![false; width]; height];
+    let dots_per_col = width / series.len().div_ceil(per_col);
+    let span = if hi > lo { hi - lo } else { 1.0 };
+    let mut prev = None;
+    for x in 0..width {
+        let v = sample(series, (x * per_col) as f64 / dots_per_col as f64);
+        let y = height - 1 - ((v - lo) / span * (height - 1) as f64).round() as usize;
+        let (top, bottom) = match prev {
+            Some(p) if p < y => (p + 1, y),
+            Some(p) if p > y => (y, p - 1),
+            _                => (y, y),
+        };
+        for row in grid[top..bottom + 1].iter_mut() {
+            row[x] = true;
+        }
+        prev = Some(y);
+    }
+    grid
+}
+
+/// The dots of character cell (`col`, `row`) as braille bits, any non-zero
+/// value when the cell is a single dot.
+fn cell(grid: &[Vec<bool>], col: usize, row: usize, dots_x: usize, dots_y: usize) -> u8 {
+    // https://en.wikipedia.org/wiki/Braille_Patterns#Identifying,_naming_and_ordering
+    static BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    let mut bits = 0;
+    for dx in 0..dots_x {
+        for dy in 0..dots_y {
+            if grid[row * dots_y + dy][col * dots_x + dx] {
+                bits |= BITS[dx][dy];
+            }
+        }
+    }
+    bits
+}
+
+/// The dots of both columns moved into the left one.
+fn left_column(bits: u8) -> u8 {
+    (bits & 0x47) | (bits & 0x38) >> 3 | (bits & 0x80) >> 1
+}
+
+fn braille(bits: u8) -> char {
+    ::std::char::from_u32(0x2800 + bits as u32).unwrap_or(' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::sample_forecast;
+    use locale::Locale;
+
+    fn render(style: ChartStyle, width: usize) -> String {
+        let mut out = Vec::new();
+        let settings = Settings { width, color: false, ..Settings::default() };
+        print_charts(&mut out, &sample_forecast(), 2, style, &settings).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(sample(&[0.0, 10.0, 20.0], 0.5), 5.0);
+        assert_eq!(sample(&[0.0, 10.0, 20.0], 7.0), 20.0);
+
+        // steep slopes are joined up
+        let grid = plot(&[0.0, 3.0], 2, 4, 1, (0.0, 3.0));
+        assert_eq!(grid.iter().map(|row| row[1]).collect::<Vec<_>>(), vec![true, true, true, false]);
+        assert_eq!(cell(&grid, 0, 0, 2, 4), 0x40 | 0x08 | 0x10 | 0x20);
+        assert_eq!(braille(0), '⠀');
+        assert_eq!(braille(0xff), '⣿');
+        assert_eq!(left_column(0x08 | 0x80 | 0x02), 0x01 | 0x40 | 0x02);
+    }
+
+    #[test]
+    fn charts() {
+        assert_eq!(ChartStyle::from_name("ascii"), Some(ChartStyle::Ascii));
+        assert_eq!(ChartStyle::from_name("braille"), None);
+
+        for &width in [125, 40].iter() {
+            for &style in [ChartStyle::Unicode, ChartStyle::Ascii].iter() {
+                let out = render(style, width);
+                let charts: Vec<&str> = out.lines().skip_while(|l| !l.contains(Locale::En.text(Text::Temperature))).collect();
+                assert_eq!(charts.iter().filter(|l| l.contains("Sun 18. Oct")).count(), 2);
+                for line in charts.iter() {
+                    assert!(line.to_string().len_on_term() <= width, "{:?}", line);
+                }
+                if style == ChartStyle::Ascii {
+                    // no box drawing, blocks or braille
+                    assert!(!charts.iter().any(|l| l.chars().any(|c| ('\u{2500}'..='\u{28ff}').contains(&c))));
+                }
+            }
+        }
+
+        // without colours, the temperature has dots in both columns of a
+        // cell and feels-like in the left one only
+        let mut forecast = sample_forecast();
+        for hour in forecast.days.iter_mut().flat_map(|d| d.hourly.iter_mut()) {
+            hour.temp_c = 10;
+            hour.feels_like_c = -10;
+        }
+        let mut out = Vec::new();
+        let settings = Settings { width: 125, color: false, ..Settings::default() };
+        print_charts(&mut out, &forecast, 2, ChartStyle::Unicode, &settings).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("⠶ Temperature  ⠆ Feels like"));
+        let rows = out.lines().skip_while(|l| !l.contains("Feels like")).skip(1)
+            .map(|l| l.chars().filter(|c| ('\u{2801}'..='\u{28ff}').contains(c)).map(|c| c as u32 - 0x2800).collect())
+            .filter(|dots: &Vec<u32>| !dots.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].iter().all(|bits| bits & 0x47 != 0 && bits & 0xb8 != 0));
+        assert!(rows[1].iter().all(|bits| bits & 0x47 != 0 && bits & 0xb8 == 0));
+
+        // 48 hours, one column each when they fit, two when they don't
+        let wide = render(ChartStyle::Ascii, 125);
+        assert!(wide.contains(&format!("       +{}", "-".repeat(48))));
+        let narrow = render(ChartStyle::Ascii, 40);
+        assert!(narrow.contains(&format!("       +{}\n", "-".repeat(24))));
+        assert!(narrow.contains("0.6 mm +"));
+    }
+}
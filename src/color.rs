@@ -0,0 +1,321 @@
+// When and how to use colours. The renderers build lines with xterm-256
+// escapes, on the way out they are stripped when colours are off, or
+// converted to what the terminal can show.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use term;
+use term::terminfo::TermInfo;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorMode {
+    /// Colours when stdout is a terminal that supports them, unless
+    /// `NO_COLOR` is set or `TERM` is "dumb".
+    Auto,
+    Always,
+    Never,
+}
+
+pub static COLOR_MODES: [&'static str; 3] = ["auto", "always", "never"];
+
+impl ColorMode {
+    pub fn from_name(name: &str) -> Option<ColorMode> {
+        match name {
+            "auto"   => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never"  => Some(ColorMode::Never),
+            _        => None,
+        }
+    }
+
+    /// Whether to colour what goes to stdout.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never  => false,
+            ColorMode::Auto   => {
+                let no_color = env::var_os("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false);
+                let dumb = env::var("TERM").map(|t| t == "dumb").unwrap_or(false);
+                !no_color && !dumb && io::stdout().is_terminal() &&
+                    term::stdout().map(|t| t.supports_color()).unwrap_or(false)
+            }
+        }
+    }
+}
+
+/// How many colours the terminal can show.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorDepth {
+    /// 24-bit colours, temperatures and wind speeds get smooth gradients.
+    TrueColor,
+    /// The xterm 256-colour palette.
+    Ansi256,
+    /// The 8 basic colours and their bright variants.
+    Ansi16,
+    /// No colours, only bold and underline.
+    Mono,
+}
+
+pub static COLOR_DEPTHS: [&'static str; 4] = ["truecolor", "256", "16", "mono"];
+
+impl ColorDepth {
+    pub fn from_name(name: &str) -> Option<ColorDepth> {
+        match name {
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256"                 => Some(ColorDepth::Ansi256),
+            "16" | "8"            => Some(ColorDepth::Ansi16),
+            "mono" | "2"          => Some(ColorDepth::Mono),
+            _                     => None,
+        }
+    }
+
+    /// `COLORTERM` for truecolor, the terminfo entry of `TERM` otherwise.
+    /// Without a terminfo entry we assume the 256-colour palette.
+    pub fn detect() -> ColorDepth {
+        match env::var("COLORTERM") {
+            Ok(ref c) if c == "truecolor" || c == "24bit" => return ColorDepth::TrueColor,
+            _                                             => (),
+        }
+        match TermInfo::from_env().ok().and_then(|ti| ti.numbers.get("colors").cloned()) {
+            Some(n) if n >= 256 => ColorDepth::Ansi256,
+            Some(n) if n >= 8   => ColorDepth::Ansi16,
+            Some(_)             => ColorDepth::Mono,
+            None                => ColorDepth::Ansi256,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Color {
+    /// Index into the xterm 256-colour palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+// xterm's defaults for the first 16 colours
+static ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255)];
+
+static CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl Color {
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb(r, g, b)           => (r, g, b),
+            Color::Indexed(i) if i < 16   => ANSI_RGB[i as usize],
+            Color::Indexed(i) if i >= 232 => (8 + 10 * (i - 232), 8 + 10 * (i - 232), 8 + 10 * (i - 232)),
+            Color::Indexed(i)             => {
+                let i = (i - 16) as usize;
+                (CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6])
+            }
+        }
+    }
+
+    /// The closest colour of the 256-colour palette.
+    pub fn to_256(self) -> u8 {
+        match self {
+            Color::Indexed(i) => i,
+            Color::Rgb(..)    => nearest(self.rgb(), 16..256),
+        }
+    }
+
+    /// The closest of the 16 basic colours, 0 to 15.
+    pub fn to_16(self) -> u8 {
+        match self {
+            Color::Indexed(i) if i < 16 => i,
+            _                           => nearest(self.rgb(), 0..16),
+        }
+    }
+
+    /// SGR parameters setting this as the foreground colour, nothing in
+    /// monochrome.
+    pub fn fg_params(self, depth: ColorDepth) -> String {
+        match depth {
+            ColorDepth::TrueColor => {
+                let (r, g, b) = self.rgb();
+                format!("38;2;{};{};{}", r, g, b)
+            }
+            ColorDepth::Ansi256   => format!("38;5;{}", self.to_256()),
+            ColorDepth::Ansi16    => match self.to_16() {
+                i if i < 8 => format!("{}", 30 + i),
+                i          => format!("{}", 90 + i - 8),
+            },
+            ColorDepth::Mono      => String::new(),
+        }
+    }
+
+    /// `text` in this colour.
+    pub fn paint(self, text: &str, depth: ColorDepth) -> String {
+        match depth {
+            ColorDepth::Mono => text.to_string(),
+            _                => format!("\u{1b}[{}m{}\u{1b}[0m", self.fg_params(depth), text),
+        }
+    }
+}
+
+fn nearest((r, g, b): (u8, u8, u8), candidates: ::std::ops::Range<u16>) -> u8 {
+    let dist = |i: &u16| {
+        let (r2, g2, b2) = Color::Indexed(*i as u8).rgb();
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    candidates.min_by_key(dist).unwrap_or(0) as u8
+}
+
+/// The colour of `value` on a scale of `(from, colour)` steps sorted by
+/// `from`. In truecolor the steps are blended into a gradient.
+pub fn scale_color(scale: &[(i32, Color)], value: i32, depth: ColorDepth) -> Color {
+    let i = scale.iter().rposition(|&(from, _)| from <= value).unwrap_or(0);
+    let (from, color) = scale[i];
+    match (depth, scale.get(i + 1)) {
+        (ColorDepth::TrueColor, Some(&(to, next))) if value >= from => {
+            let t = (value - from) as f32 / (to - from) as f32;
+            let ((r1, g1, b1), (r2, g2, b2)) = (color.rgb(), next.rgb());
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+            Color::Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+        }
+        _ => color,
+    }
+}
+
+/// Rewrites the xterm-256 colours in `s` for `depth`. In monochrome only
+/// bold, underline and resets are kept.
+pub fn convert(s: &str, depth: ColorDepth) -> String {
+    if depth == ColorDepth::Ansi256 {
+        return s.to_string();
+    }
+    recolor(s, depth, &Color::Indexed)
+}
+
+/// Like `convert`, replacing each xterm-256 colour with `color(index)`.
+pub fn recolor(s: &str, depth: ColorDepth, color: &dyn Fn(u8) -> Color) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("\u{1b}[") {
+        ret.push_str(&rest[..start]);
+        let seq = &rest[start + 2..];
+        let end = match seq.find('m') {
+            Some(end) => end,
+            None      => { rest = &rest[start..]; break; }
+        };
+
+        let params = seq[..end].split(';').collect::<Vec<_>>();
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < params.len() {
+            match (params[i], params.get(i + 1), params.get(i + 2).and_then(|n| n.parse().ok())) {
+                ("38", Some(&"5"), Some(n)) => {
+                    let params = color(n).fg_params(depth);
+                    if !params.is_empty() {
+                        out.push(params);
+                    }
+                    i += 3;
+                    continue;
+                }
+                ("0", _, _) | ("", _, _) | ("1", _, _) | ("4", _, _) | ("22", _, _) | ("24", _, _) =>
+                    out.push(params[i].to_string()),
+                (p, _, _) if depth != ColorDepth::Mono =>
+                    out.push(p.to_string()),
+                _ => (),
+            }
+            i += 1;
+        }
+        if !out.is_empty() {
+            ret.push_str("\u{1b}[");
+            ret.push_str(&out.join(";"));
+            ret.push('m');
+        }
+        rest = &seq[end + 1..];
+    }
+    ret.push_str(rest);
+    ret
+}
+
+/// Removes the escape sequences, leaving the text and layout alone.
+pub fn strip(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut wait_for_color_mark_ends = false;
+
+    for c in s.chars() {
+        if c == '\u{1b}' && !wait_for_color_mark_ends {
+            wait_for_color_mark_ends = true;
+        } else if c == 'm' && wait_for_color_mark_ends {
+            wait_for_color_mark_ends = false;
+        } else if !wait_for_color_mark_ends {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for name in COLOR_MODES.iter() {
+            assert!(ColorMode::from_name(name).is_some());
+        }
+        assert_eq!(ColorMode::from_name("sometimes"), None);
+    }
+
+    #[test]
+    fn forced_modes() {
+        assert!(ColorMode::Always.enabled());
+        assert!(!ColorMode::Never.enabled());
+    }
+
+    #[test]
+    fn depths() {
+        for name in COLOR_DEPTHS.iter() {
+            assert!(ColorDepth::from_name(name).is_some());
+        }
+        assert_eq!(ColorDepth::from_name("24bit"), Some(ColorDepth::TrueColor));
+        assert_eq!(ColorDepth::from_name("lots"), None);
+    }
+
+    #[test]
+    fn palettes() {
+        assert_eq!(Color::Indexed(226).rgb(), (255, 255, 0));
+        assert_eq!(Color::Indexed(240).rgb(), (88, 88, 88));
+        assert_eq!(Color::Indexed(9).rgb(), (255, 0, 0));
+        assert_eq!(Color::Rgb(255, 255, 0).to_256(), 226);
+        assert_eq!(Color::Indexed(226).to_16(), 11);
+        assert_eq!(Color::Indexed(21).to_16(), 4);
+        assert_eq!(Color::Indexed(111).to_16(), 12);
+        assert_eq!(Color::Indexed(226).fg_params(ColorDepth::Ansi16), "93");
+        assert_eq!(Color::Indexed(226).fg_params(ColorDepth::TrueColor), "38;2;255;255;0");
+        assert_eq!(Color::Indexed(226).paint("x", ColorDepth::Mono), "x");
+    }
+
+    #[test]
+    fn scales() {
+        let scale = [(0, Color::Rgb(0, 0, 0)), (10, Color::Rgb(100, 200, 0))];
+        assert_eq!(scale_color(&scale, -5, ColorDepth::Ansi256), Color::Rgb(0, 0, 0));
+        assert_eq!(scale_color(&scale, 5, ColorDepth::Ansi256), Color::Rgb(0, 0, 0));
+        assert_eq!(scale_color(&scale, 5, ColorDepth::TrueColor), Color::Rgb(50, 100, 0));
+        assert_eq!(scale_color(&scale, 12, ColorDepth::TrueColor), Color::Rgb(100, 200, 0));
+    }
+
+    #[test]
+    fn conversions() {
+        let line = "\u{1b}[38;5;240;1m .-. \u{1b}[38;5;228;5m⚡\u{1b}[0m";
+        assert_eq!(convert(line, ColorDepth::Ansi256), line);
+        assert_eq!(convert(line, ColorDepth::TrueColor),
+                   "\u{1b}[38;2;88;88;88;1m .-. \u{1b}[38;2;255;255;135;5m⚡\u{1b}[0m");
+        assert_eq!(convert(line, ColorDepth::Ansi16), "\u{1b}[90;1m .-. \u{1b}[37;5m⚡\u{1b}[0m");
+        assert_eq!(convert(line, ColorDepth::Mono), "\u{1b}[1m .-. ⚡\u{1b}[0m");
+        assert_eq!(strip(&convert(line, ColorDepth::Mono)), strip(line));
+    }
+
+    #[test]
+    fn strip_escapes() {
+        assert_eq!(strip("\u{1b}[38;5;226m 22\u{1b}[0m °C"), " 22 °C");
+        assert_eq!(strip("\u{1b}[1m↓\u{1b}[0m 天气"), "↓ 天气");
+        assert_eq!(strip("plain"), "plain");
+    }
+}
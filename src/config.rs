@@ -0,0 +1,217 @@
+// User defaults, read from $XDG_CONFIG_HOME/weather/config.toml:
+//
+//     location = "Guangzhou"
+//     locations = ["Guangzhou", "Berlin"]
+//     days = 5
+//     units = "imperial"
+//     locale = "zh-cn"
+//     backend = "owm"
+//     key = "0123456789abcdef"
+//     color = "never"
+//     color_depth = "16"
+//     theme = "light-background"
+//     slots = [6, 9, 12, 15, 18, 21]
+//     details = true
+//     gazetteer = "/usr/share/geonames/cities500.txt"
+//
+// Every key is optional, command line options take precedence.
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use toml::{Parser, Table, Value};
+
+use error::{Error, Result};
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub location: Option<String>,
+    /// Shown together, takes precedence over `location`.
+    pub locations: Option<Vec<String>>,
+    pub days: Option<usize>,
+    pub units: Option<String>,
+    pub locale: Option<String>,
+    pub backend: Option<String>,
+    pub key: Option<String>,
+    pub color: Option<String>,
+    pub color_depth: Option<String>,
+    pub theme: Option<String>,
+    /// Hours of the day-part slots.
+    pub slots: Option<Vec<u32>>,
+    /// Humidity, cloud cover, UV index and pressure lines.
+    pub details: Option<bool>,
+    /// GeoNames dump for offline geocoding.
+    pub gazetteer: Option<String>,
+}
+
+impl Config {
+    /// $XDG_CONFIG_HOME/weather/config.toml, or ~/.config/weather/config.toml
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+            _                                => env::home_dir()?.join(".config"),
+        };
+        Some(base.join("weather").join("config.toml"))
+    }
+
+    /// Loads the default config file, a missing file is an empty config.
+    pub fn load_default() -> Result<Config> {
+        match Config::default_path() {
+            Some(ref path) if path.exists() => Config::load(path),
+            _                               => Ok(Config::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Config> {
+        let mut buf = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut buf))
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+        Config::parse(&buf).map_err(|e| match e {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            e                  => e,
+        })
+    }
+
+    pub fn parse(s: &str) -> Result<Config> {
+        let mut parser = Parser::new(s);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None        => {
+                let err = &parser.errors[0];
+                let (line, col) = parser.to_linecol(err.lo);
+                return Err(Error::Config(format!("{}:{}: {}", line + 1, col + 1, err.desc)));
+            }
+        };
+
+        let days = match table.get("days") {
+            Some(&Value::Integer(n)) if n > 0 => Some(n as usize),
+            Some(_)                           => return Err(Error::Config("`days` must be a positive integer".to_string())),
+            None                              => None,
+        };
+
+        let slots = match table.get("slots") {
+            Some(Value::Array(hours)) if !hours.is_empty() => {
+                let mut slots = Vec::with_capacity(hours.len());
+                for hour in hours {
+                    match *hour {
+                        Value::Integer(h) if (0..24).contains(&h) => slots.push(h as u32),
+                        _ => return Err(Error::Config("`slots` must be hours from 0 to 23".to_string())),
+                    }
+                }
+                slots.sort();
+                slots.dedup();
+                Some(slots)
+            }
+            Some(_) => return Err(Error::Config("`slots` must be a list of hours".to_string())),
+            None    => None,
+        };
+
+        let locations = match table.get("locations") {
+            Some(Value::Array(values)) if !values.is_empty() => {
+                let mut locations = Vec::with_capacity(values.len());
+                for value in values {
+                    match *value {
+                        Value::String(ref s) => locations.push(s.clone()),
+                        _ => return Err(Error::Config("`locations` must be a list of strings".to_string())),
+                    }
+                }
+                Some(locations)
+            }
+            Some(_) => return Err(Error::Config("`locations` must be a list of strings".to_string())),
+            None    => None,
+        };
+
+        let details = match table.get("details") {
+            Some(&Value::Boolean(b)) => Some(b),
+            Some(_)                  => return Err(Error::Config("`details` must be true or false".to_string())),
+            None                     => None,
+        };
+
+        Ok(Config {
+            location: string_value(&table, "location")?,
+            locations,
+            days,
+            units: string_value(&table, "units")?,
+            locale: string_value(&table, "locale")?,
+            backend: string_value(&table, "backend")?,
+            key: string_value(&table, "key")?,
+            color: string_value(&table, "color")?,
+            color_depth: string_value(&table, "color_depth")?,
+            theme: string_value(&table, "theme")?,
+            slots,
+            details,
+            gazetteer: string_value(&table, "gazetteer")?,
+        })
+    }
+}
+
+fn string_value(table: &Table, key: &str) -> Result<Option<String>> {
+    match table.get(key) {
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_)                => Err(Error::Config(format!("`{}` must be a string", key))),
+        None                   => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_config() {
+        let config = Config::parse(r#"
+            location = "New York"
+            locations = ["Beijing", "Berlin"]
+            days = 5
+            units = "uk"
+            locale = "zh-cn"
+            backend = "owm"
+            key = "secret"
+            color = "never"
+            color_depth = "16"
+            theme = "solarized-dark"
+            slots = [18, 6, 12]
+            details = true
+            gazetteer = "/usr/share/geonames/cities500.txt"
+        "#).unwrap();
+
+        assert_eq!(config.location, Some("New York".to_string()));
+        assert_eq!(config.locations, Some(vec!["Beijing".to_string(), "Berlin".to_string()]));
+        assert_eq!(config.days, Some(5));
+        assert_eq!(config.units, Some("uk".to_string()));
+        assert_eq!(config.locale, Some("zh-cn".to_string()));
+        assert_eq!(config.backend, Some("owm".to_string()));
+        assert_eq!(config.key, Some("secret".to_string()));
+        assert_eq!(config.color, Some("never".to_string()));
+        assert_eq!(config.color_depth, Some("16".to_string()));
+        assert_eq!(config.theme, Some("solarized-dark".to_string()));
+        assert_eq!(config.slots, Some(vec![6, 12, 18]));
+        assert_eq!(config.details, Some(true));
+        assert_eq!(config.gazetteer, Some("/usr/share/geonames/cities500.txt".to_string()));
+    }
+
+    #[test]
+    fn everything_is_optional() {
+        let config = Config::parse("").unwrap();
+        assert!(config.location.is_none());
+        assert!(config.days.is_none());
+    }
+
+    #[test]
+    fn bad_values() {
+        assert!(Config::parse("days = \"three\"").is_err());
+        assert!(Config::parse("days = 0").is_err());
+        assert!(Config::parse("location = 42").is_err());
+        assert!(Config::parse("locations = \"Berlin\"").is_err());
+        assert!(Config::parse("locations = [1, 2]").is_err());
+        assert!(Config::parse("slots = [9, 24]").is_err());
+        assert!(Config::parse("slots = []").is_err());
+        assert!(Config::parse("details = \"yes\"").is_err());
+        match Config::parse("location = ") {
+            Err(Error::Config(msg)) => assert!(msg.starts_with("1:")),
+            other                   => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Bad command line option or config value.
+    Usage(String),
+    /// The config file can't be read or parsed.
+    Config(String),
+    /// The service couldn't be reached.
+    Network(String),
+    /// The service answered with an HTTP error status and nothing we understand.
+    Http(u16),
+    /// The service doesn't know the location.
+    LocationNotFound(String),
+    /// The service refused the request, e.g. a bad API key or quota exceeded.
+    Provider(String),
+    /// The response isn't what we expected.
+    Decode(String),
+    /// A date in the response can't be parsed.
+    Date(String),
+    /// Writing the output failed.
+    Io(io::Error),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl Error {
+    /// Process exit code, one per kind of failure so scripts can tell
+    /// "city not found" from "service down".
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Io(_)               => 1,
+            Error::Usage(_)            => 2,
+            Error::Config(_)           => 3,
+            Error::LocationNotFound(_) => 4,
+            Error::Provider(_)         => 5,
+            Error::Network(_)          => 6,
+            Error::Http(_)             => 7,
+            Error::Decode(_)           => 8,
+            Error::Date(_)             => 9,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Usage(ref msg)            => write!(f, "{}", msg),
+            Error::Config(ref msg)           => write!(f, "config: {}", msg),
+            Error::Network(ref msg)          => write!(f, "unable to reach the weather service: {}", msg),
+            Error::Http(status)              => write!(f, "the weather service answered with HTTP {}", status),
+            Error::LocationNotFound(ref loc) => write!(f, "location not found: {}", loc),
+            Error::Provider(ref msg)         => write!(f, "the weather service refused the request: {}", msg),
+            Error::Decode(ref msg)           => write!(f, "unable to decode the response: {}", msg),
+            Error::Date(ref date)            => write!(f, "invalid date in the response: {}", date),
+            Error::Io(ref err)               => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
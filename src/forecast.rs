@@ -0,0 +1,158 @@
+// Provider-neutral forecast model. Backends decode whatever their service
+// returns into these types; the renderers only ever see these.
+
+/// Weather conditions, one per icon in the icon table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Condition {
+    Unknown,
+    Sunny,
+    PartlyCloudy,
+    Cloudy,
+    VeryCloudy,
+    Fog,
+    LightShowers,
+    HeavyShowers,
+    LightSnowShowers,
+    HeavySnowShowers,
+    LightSleetShowers,
+    ThunderyShowers,
+    ThunderyHeavyRain,
+    ThunderySnowShowers,
+    LightRain,
+    HeavyRain,
+    LightSnow,
+    HeavySnow,
+    LightSleet,
+}
+
+impl Condition {
+    /// Stable snake_case name, used in the JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Condition::Unknown             => "unknown",
+            Condition::Sunny               => "sunny",
+            Condition::PartlyCloudy        => "partly_cloudy",
+            Condition::Cloudy              => "cloudy",
+            Condition::VeryCloudy          => "very_cloudy",
+            Condition::Fog                 => "fog",
+            Condition::LightShowers        => "light_showers",
+            Condition::HeavyShowers        => "heavy_showers",
+            Condition::LightSnowShowers    => "light_snow_showers",
+            Condition::HeavySnowShowers    => "heavy_snow_showers",
+            Condition::LightSleetShowers   => "light_sleet_showers",
+            Condition::ThunderyShowers     => "thundery_showers",
+            Condition::ThunderyHeavyRain   => "thundery_heavy_rain",
+            Condition::ThunderySnowShowers => "thundery_snow_showers",
+            Condition::LightRain           => "light_rain",
+            Condition::HeavyRain           => "heavy_rain",
+            Condition::LightSnow           => "light_snow",
+            Condition::HeavySnow           => "heavy_snow",
+            Condition::LightSleet          => "light_sleet",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Forecast {
+    pub location: String,
+    /// Latitude and longitude, when the provider tells.
+    pub coordinates: Option<(f64, f64)>,
+    /// Offset of the local time from UTC, in seconds.
+    pub utc_offset: Option<i32>,
+    pub current: WeatherCondition,
+    pub days: Vec<Weather>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WeatherCondition {
+    pub condition: Condition,
+    /// Description in English, as given by the provider.
+    pub desc: String,
+    /// Description in the requested language, when the provider has one.
+    pub local_desc: Option<String>,
+    /// Local hour of the day, for hourly entries.
+    pub hour: Option<u32>,
+    pub temp_c: i32,
+    pub feels_like_c: i32,
+    pub humidity: i32,
+    pub cloud_cover: i32,
+    pub precip_mm: f32,
+    pub chance_of_rain: Option<i32>,
+    pub visibility_km: i32,
+    /// 16-point compass direction the wind blows from, e.g. "NNE".
+    pub wind_dir: String,
+    pub wind_speed_kmph: i32,
+    pub wind_gust_kmph: Option<i32>,
+    /// At sea level.
+    pub pressure_hpa: Option<i32>,
+    pub dewpoint_c: Option<i32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Weather {
+    /// Local date, "%Y-%m-%d".
+    pub date: String,
+    pub astronomy: Astronomy,
+    pub hourly: Vec<WeatherCondition>,
+    pub max_temp_c: i32,
+    pub min_temp_c: i32,
+    pub uv_index: Option<i32>,
+}
+
+impl Weather {
+    /// The hourly entry closest to `hour`, so that 1-hourly and 3-hourly
+    /// data give the same slots.
+    pub fn at_hour(&self, hour: u32) -> Option<&WeatherCondition> {
+        self.hourly.iter()
+            .filter_map(|h| h.hour.map(|hh| (hh, h)))
+            .min_by_key(|&(hh, _)| (hh as i32 - hour as i32).abs())
+            .map(|(_, h)| h)
+    }
+
+    /// An entry with the condition of most of the daytime entries, from 6
+    /// to 21 o'clock, the earliest one on a tie.
+    pub fn dominant(&self) -> Option<&WeatherCondition> {
+        let daytime = self.hourly.iter()
+            .filter(|h| h.hour.is_none_or(|hour| (6..=21).contains(&hour)))
+            .collect::<Vec<_>>();
+        let entries = if daytime.is_empty() { self.hourly.iter().collect() } else { daytime };
+        entries.iter()
+            .rev()
+            .max_by_key(|h| entries.iter().filter(|e| e.condition == h.condition).count())
+            .cloned()
+    }
+}
+
+/// Local times as "HH:MM", `None` when the event doesn't happen that day.
+#[derive(Clone, Debug, Default)]
+pub struct Astronomy {
+    pub sunrise: Option<String>,
+    pub sunset: Option<String>,
+    pub moonrise: Option<String>,
+    pub moonset: Option<String>,
+    /// Age in the lunar cycle, 0 is new moon and 0.5 full moon.
+    pub moon_phase: Option<f64>,
+    /// Lit fraction of the moon's disc, in %.
+    pub moon_illumination: Option<i32>,
+}
+
+impl Astronomy {
+    /// Minutes between sunrise and sunset.
+    pub fn day_length(&self) -> Option<i32> {
+        fn minutes(t: &Option<String>) -> Option<i32> {
+            let t = t.as_ref()?;
+            let mut parts = t.splitn(2, ':').map(|p| p.parse::<i32>().ok());
+            Some(parts.next()?? * 60 + parts.next()??)
+        }
+        Some(minutes(&self.sunset)? - minutes(&self.sunrise)?)
+    }
+}
+
+
+/// Maps a wind direction in degrees onto the 16-point compass.
+pub fn degrees_to_16_point(deg: f64) -> &'static str {
+    static POINTS: [&'static str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+        "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW"];
+    POINTS[((deg.rem_euclid(360.0) / 22.5).round() as usize) % 16]
+}
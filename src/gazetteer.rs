@@ -0,0 +1,418 @@
+// Offline geocoding from a GeoNames dump, cities500.txt by default, next to
+// the config file:
+//
+//     https://download.geonames.org/export/dump/cities500.zip
+//     https://download.geonames.org/export/dump/admin1CodesASCII.txt
+//     https://download.geonames.org/export/dump/countryInfo.txt
+//
+// The region names come from admin1CodesASCII.txt, the country names from
+// countryInfo.txt, when they are in the same directory. A place is found by
+// its name, its ASCII spelling or any of its alternate names, and narrowed
+// down by what follows a comma, a country or a region, by code or by name:
+//
+//     Springfield, IL
+//     Paris, Texas, US
+//     Paris, France
+//
+// `lookup` takes the exact spelling only, `search` a typo or two as well. The
+// most populous of the best matches comes first. Looking up a few names needs
+// only the lines that mention them, `load_for` skips the others unparsed.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use config::Config;
+use error::{Error, Result};
+use locale::Locale;
+use location;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Place {
+    pub name: String,
+    /// The admin1 region, if its name is known.
+    pub region: Option<String>,
+    /// ISO 3166 code, e.g. "DE".
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub population: u64,
+    /// GeoNames' admin1 code, e.g. "IL" or "16".
+    admin1: String,
+    /// Every spelling, lowercase.
+    keys: Vec<String>,
+    /// The alternate names in Chinese, Japanese or Russian script, as they
+    /// are spelt.
+    local_names: Vec<String>,
+}
+
+impl Place {
+    /// "Springfield, Illinois, US", or "北京, CN" and "Берлин, Land Berlin,
+    /// DE" in a locale whose script the place has a name in.
+    pub fn display_name(&self, locale: Locale) -> String {
+        location::display_name(self.local_name(locale), self.region.as_ref().map(|r| &r[..]), Some(&self.country))
+    }
+
+    /// The first alternate name in `locale`'s script, kana before kanji in
+    /// Japanese, or the name itself. The dump doesn't say which language
+    /// an alternate name is in, the script is all there is to go by.
+    pub fn local_name(&self, locale: Locale) -> &str {
+        let scripts: &[fn(char) -> bool] = match locale {
+            Locale::ZhCn | Locale::ZhTw => &[is_han],
+            Locale::Ja                  => &[is_kana, is_han],
+            Locale::Ru                  => &[is_russian],
+            _                           => &[],
+        };
+        scripts.iter()
+            .filter_map(|&script| self.local_names.iter().find(|n| written_in(n, script)))
+            .next()
+            .map_or(&self.name[..], |n| &n[..])
+    }
+
+    /// Whether "IL", "Illinois", "US" or `countries`' "USA" and "United
+    /// States" describe the place.
+    fn matches(&self, qualifier: &str, countries: &HashMap<String, Vec<String>>) -> bool {
+        let q = qualifier.to_lowercase();
+        q == self.country.to_lowercase()
+            || q == self.admin1.to_lowercase()
+            || self.region.as_ref().is_some_and(|r| q == r.to_lowercase())
+            || countries.get(&self.country).is_some_and(|names| names.contains(&q))
+    }
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}')
+}
+
+// "ベルリン" or "フランクフルト・アム・マイン"
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}')
+}
+
+// the Russian letters, not Ukrainian's і or ї
+fn is_russian(c: char) -> bool {
+    matches!(c, 'А'..='я' | 'Ё' | 'ё')
+}
+
+/// Whether `name` is in `script`, but for spaces and hyphens ("Нью-Йорк").
+fn written_in(name: &str, script: fn(char) -> bool) -> bool {
+    name.chars().any(script) && name.chars().all(|c| script(c) || c == ' ' || c == '-')
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Gazetteer {
+    pub places: Vec<Place>,
+    /// Country code to its other names, lowercase.
+    countries: HashMap<String, Vec<String>>,
+}
+
+impl Gazetteer {
+    /// cities500.txt next to the default config file.
+    pub fn default_path() -> Option<PathBuf> {
+        Config::default_path().map(|path| path.with_file_name("cities500.txt"))
+    }
+
+    /// Loads a GeoNames dump, and admin1CodesASCII.txt and countryInfo.txt
+    /// beside it, if any.
+    pub fn load(path: &Path) -> Result<Gazetteer> {
+        Gazetteer::load_lines(path, &[])
+    }
+
+    /// Like `load`, keeping only the places whose line mentions one of
+    /// `queries`, enough to `lookup` them.
+    pub fn load_for(path: &Path, queries: &[&str]) -> Result<Gazetteer> {
+        // "Springfield, IL" => "springfield"
+        let names = queries.iter()
+            .map(|q| q.split(',').next().unwrap_or("").trim().to_lowercase())
+            .filter(|n| !n.is_empty())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Ok(Gazetteer::default());
+        }
+        Gazetteer::load_lines(path, &names)
+    }
+
+    /// Loads the lines mentioning one of `names`, in lowercase, or all of
+    /// them when there are none.
+    fn load_lines(path: &Path, names: &[String]) -> Result<Gazetteer> {
+        if !path.exists() {
+            return Err(Error::Config(format!("{}: no gazetteer, get cities500.txt from \
+                                              https://download.geonames.org/export/dump/", path.display())));
+        }
+        let read = |path: &Path| {
+            let mut buf = String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut buf))
+                .map(|_| buf)
+                .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+        };
+        let beside = |name: &str| {
+            let path = path.with_file_name(name);
+            if path.exists() { read(&path) } else { Ok(String::new()) }
+        };
+        let cities = read(path)?;
+        let (admin1, countries) = (beside("admin1CodesASCII.txt")?, beside("countryInfo.txt")?);
+        let result = if names.is_empty() {
+            Gazetteer::parse_lines(cities.lines().enumerate(), &admin1, &countries)
+        } else {
+            // lowercasing keeps the lines, though not their lengths, so the
+            // names are found in a lowercase copy and kept by line number
+            let lower = cities.to_lowercase();
+            let mut wanted = HashSet::new();
+            for name in names {
+                let (mut line, mut start) = (0, 0);
+                for (at, _) in lower.match_indices(&name[..]) {
+                    if let Some(end) = lower[start..at].rfind('\n') {
+                        line += lower[start..at].matches('\n').count();
+                        start += end + 1;
+                    }
+                    // in the name, ASCII name or alternate names, not the
+                    // time zone (Europe/Berlin) of every town around
+                    if (1..4).contains(&lower[start..at].matches('\t').count()) {
+                        wanted.insert(line);
+                    }
+                }
+            }
+            let lines = cities.lines().enumerate().filter(|&(i, _)| wanted.contains(&i));
+            Gazetteer::parse_lines(lines, &admin1, &countries)
+        };
+        result.map_err(|e| match e {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            e                  => e,
+        })
+    }
+
+    /// Parses the tab separated places, admin1 codes and countries.
+    pub fn parse(cities: &str, admin1: &str, countries: &str) -> Result<Gazetteer> {
+        Gazetteer::parse_lines(cities.lines().enumerate(), admin1, countries)
+    }
+
+    /// Parses the places from numbered lines, 0 being the first.
+    fn parse_lines<'a, I>(lines: I, admin1: &str, countries: &str) -> Result<Gazetteer>
+        where I: Iterator<Item = (usize, &'a str)> {
+        // "US\tUSA\t840\tUS\tUnited States\t...", after # comments
+        let countries = countries.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let cols = line.split('\t').collect::<Vec<_>>();
+                if cols.len() < 5 {
+                    return None;
+                }
+                Some((cols[0].to_string(), vec![cols[1].to_lowercase(), cols[4].to_lowercase()]))
+            })
+            .collect::<HashMap<_, _>>();
+
+        // "US.IL\tIllinois\tIllinois\t4896861"
+        let regions = admin1.lines()
+            .filter_map(|line| {
+                let mut cols = line.split('\t');
+                Some((cols.next()?, cols.next()?))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut places = Vec::new();
+        for (i, line) in lines.filter(|&(_, l)| !l.trim().is_empty()) {
+            let cols = line.split('\t').collect::<Vec<_>>();
+            let invalid = |what: &str| Error::Config(format!("{}: {}", i + 1, what));
+            // geonameid, name, asciiname, alternatenames, latitude, longitude,
+            // feature class, feature code, country code, cc2, admin1 code, ...,
+            // population at 14
+            if cols.len() < 15 {
+                return Err(invalid("expected the tab separated GeoNames columns"));
+            }
+            let coordinate = |s: &str| s.parse::<f64>().map_err(|_| invalid("invalid coordinates"));
+            let (name, country, admin1) = (cols[1], cols[8], cols[10]);
+
+            let mut keys = vec![name.to_lowercase(), cols[2].to_lowercase()];
+            keys.extend(cols[3].split(',').filter(|n| !n.is_empty()).map(|n| n.to_lowercase()));
+            keys.sort();
+            keys.dedup();
+            let local_names = cols[3].split(',')
+                .filter(|n| [is_han, is_kana, is_russian].iter().any(|&script| written_in(n, script)))
+                .map(|n| n.to_string())
+                .collect();
+
+            places.push(Place {
+                name: name.to_string(),
+                // the region of a city state is the city itself
+                region: regions.get(&format!("{}.{}", country, admin1)[..])
+                    .filter(|r| **r != cols[2])
+                    .map(|r| r.to_string()),
+                country: country.to_string(),
+                latitude: coordinate(cols[4])?,
+                longitude: coordinate(cols[5])?,
+                population: cols[14].parse().unwrap_or(0),
+                admin1: admin1.to_string(),
+                keys,
+                local_names,
+            });
+        }
+        Ok(Gazetteer { places, countries })
+    }
+
+    /// The most populous place called exactly `query`, any case.
+    pub fn lookup(&self, query: &str) -> Option<&Place> {
+        self.find(query, false).into_iter().next()
+    }
+
+    /// The places called `query` or close to it, the closest spelling first,
+    /// then the most populous.
+    pub fn search(&self, query: &str) -> Vec<&Place> {
+        self.find(query, true)
+    }
+
+    fn find(&self, query: &str, fuzzy: bool) -> Vec<&Place> {
+        let mut parts = query.split(',').map(|p| p.trim());
+        let name = parts.next().unwrap_or("").to_lowercase();
+        let qualifiers = parts.filter(|p| !p.is_empty()).collect::<Vec<_>>();
+        if name.is_empty() {
+            return Vec::new();
+        }
+
+        // a typo in a short name is another name
+        let len = name.chars().count();
+        let tolerance = match len {
+            _ if !fuzzy => 0,
+            0..=3       => 0,
+            4..=7       => 1,
+            _           => 2,
+        };
+
+        let mut found = self.places.iter()
+            .filter(|place| qualifiers.iter().all(|q| place.matches(q, &self.countries)))
+            .filter_map(|place| {
+                place.keys.iter()
+                    .filter(|key| key.chars().count().abs_diff(len) <= tolerance)
+                    .map(|key| if *key == name { 0 } else if fuzzy { edit_distance(&name, key) } else { 1 })
+                    .filter(|&d| d <= tolerance)
+                    .min()
+                    .map(|d| (d, place))
+            })
+            .collect::<Vec<_>>();
+        found.sort_by_key(|&(d, place)| (d, Reverse(place.population)));
+        found.into_iter().map(|(_, place)| place).collect()
+    }
+}
+
+/// Levenshtein distance, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb { diagonal } else { 1 + diagonal.min(above).min(row[j]) };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn gazetteer() -> Gazetteer {
+        Gazetteer::load(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/geonames/cities500.txt")))
+            .unwrap()
+    }
+
+    fn names(places: Vec<&Place>) -> Vec<String> {
+        places.iter().map(|p| p.display_name(Locale::En)).collect()
+    }
+
+    #[test]
+    fn load() {
+        let gazetteer = gazetteer();
+        assert_eq!(gazetteer.places.len(), 11);
+        let berlin = &gazetteer.search("Berlin")[0];
+        assert_eq!(berlin.display_name(Locale::En), "Berlin, Land Berlin, DE");
+        assert_eq!((berlin.latitude, berlin.longitude), (52.52437, 13.41053));
+        assert_eq!(berlin.population, 3426354);
+        assert_eq!(gazetteer.search("Beijing")[0].display_name(Locale::En), "Beijing, CN");
+
+        assert!(Gazetteer::parse("2950159\tBerlin\tBerlin", "", "").is_err());
+        assert!(Gazetteer::load(Path::new("/nonexistent/cities500.txt")).is_err());
+    }
+
+    #[test]
+    fn load_for() {
+        let path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/geonames/cities500.txt"));
+        let (all, some) = (gazetteer(), Gazetteer::load_for(path, &["berlin, de", "PARIS"]).unwrap());
+        assert!(some.places.len() < all.places.len());
+        assert_eq!(some.lookup("Berlin, DE"), all.lookup("Berlin, DE"));
+        assert_eq!(some.lookup("Paris").map(|p| &p.name[..]), Some("Paris"));
+        assert_eq!(some.lookup("Beijing"), None);
+        // alternate names count too
+        assert_eq!(Gazetteer::load_for(path, &["Берлин"]).unwrap().lookup("Берлин").map(|p| &p.name[..]), Some("Berlin"));
+        // not by their time zone
+        assert!(Gazetteer::load_for(path, &["Europe"]).unwrap().places.is_empty());
+        assert!(Gazetteer::load_for(path, &[]).unwrap().places.is_empty());
+    }
+
+    #[test]
+    fn local_names() {
+        let gazetteer = gazetteer();
+        let name = |q, locale| gazetteer.lookup(q).unwrap().display_name(locale);
+        assert_eq!(name("Beijing", Locale::ZhCn), "北京, CN");
+        assert_eq!(name("Beijing", Locale::De), "Beijing, CN");
+        assert_eq!(name("Berlin", Locale::Ru), "Берлин, Land Berlin, DE");
+        assert_eq!(name("Berlin", Locale::ZhTw), "柏林, Land Berlin, DE");
+        // kana before kanji
+        assert_eq!(name("Berlin", Locale::Ja), "ベルリン, Land Berlin, DE");
+        assert_eq!(name("Guangzhou", Locale::Ja), "广州, Guangdong, CN");
+        // none in Cyrillic
+        assert_eq!(name("Guangzhou", Locale::Ru), "Guangzhou, Guangdong, CN");
+    }
+
+    #[test]
+    fn spellings() {
+        let gazetteer = gazetteer();
+        let first = |q| gazetteer.search(q).first().map(|p| p.name.clone());
+        assert_eq!(first("zurich"), Some("Zürich".to_string()));
+        assert_eq!(first("Peking"), Some("Beijing".to_string()));
+        assert_eq!(first("北京"), Some("Beijing".to_string()));
+        assert_eq!(first("Berln"), Some("Berlin".to_string()));
+        assert_eq!(first("Guangzou"), Some("Guangzhou".to_string()));
+        assert_eq!(first("Frankfurt"), Some("Frankfurt am Main".to_string()));
+        assert_eq!(first("Atlantis"), None);
+        assert_eq!(first("Rom"), None);
+
+        // no typos when it's not a search
+        assert_eq!(gazetteer.lookup("peking").map(|p| &p.name[..]), Some("Beijing"));
+        assert_eq!(gazetteer.lookup("Frankfurt").map(|p| &p.name[..]), Some("Frankfurt am Main"));
+        assert_eq!(gazetteer.lookup("Berln"), None);
+        assert_eq!(gazetteer.lookup("Springfield, IL").map(|p| p.population), Some(116565));
+    }
+
+    #[test]
+    fn disambiguation() {
+        let gazetteer = gazetteer();
+        assert_eq!(names(gazetteer.search("Springfield")),
+                   vec!["Springfield, Missouri, US", "Springfield, Massachusetts, US", "Springfield, Illinois, US"]);
+        assert_eq!(names(gazetteer.search("springfield, il")), vec!["Springfield, Illinois, US"]);
+        assert_eq!(names(gazetteer.search("Springfield, Illinois")), vec!["Springfield, Illinois, US"]);
+        assert_eq!(names(gazetteer.search("Paris")), vec!["Paris, Île-de-France, FR", "Paris, Texas, US"]);
+        assert_eq!(names(gazetteer.search("Paris, Texas, US")), vec!["Paris, Texas, US"]);
+        assert_eq!(names(gazetteer.search("Paris, France")), vec!["Paris, Île-de-France, FR"]);
+        assert_eq!(names(gazetteer.search("paris, usa")), vec!["Paris, Texas, US"]);
+        assert_eq!(gazetteer.search("Springfield, United States").len(), 3);
+        assert!(gazetteer.search("Paris, DE").is_empty());
+        assert!(gazetteer.search("Paris, Germany").is_empty());
+        assert!(gazetteer.search(", FR").is_empty());
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("berlin", "berlin"), 0);
+        assert_eq!(edit_distance("berln", "berlin"), 1);
+        assert_eq!(edit_distance("zürich", "zurich"), 1);
+        assert_eq!(edit_distance("", "rom"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,136 @@
+// JSON output. The schema is ours, not the provider's: it only changes
+// together with `SCHEMA_VERSION`, see "JSON output" in the README.
+//
+// Values are always metric, whatever the unit system, and the field names
+// say so.
+
+use std::collections::BTreeMap;
+use std::io::prelude::*;
+use rustc_serialize::json::{Json, ToJson};
+
+use error::Result;
+use forecast::{Astronomy, Forecast, Weather, WeatherCondition};
+use locale::Locale;
+
+pub static SCHEMA_VERSION: u64 = 2;
+
+/// The location, the current condition and the first `days` days.
+pub fn to_json(forecast: &Forecast, days: usize, locale: Locale) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("version".to_string(), SCHEMA_VERSION.to_json());
+    obj.insert("location".to_string(), forecast.location.to_json());
+    obj.insert("latitude".to_string(), forecast.coordinates.map(|c| c.0).to_json());
+    obj.insert("longitude".to_string(), forecast.coordinates.map(|c| c.1).to_json());
+    obj.insert("locale".to_string(), locale.name().to_json());
+    obj.insert("current".to_string(), condition_to_json(&forecast.current, locale));
+    obj.insert("days".to_string(), Json::Array(forecast.days.iter()
+                                                   .take(days)
+                                                   .map(|d| day_to_json(d, locale))
+                                                   .collect()));
+    Json::Object(obj)
+}
+
+pub fn print_json(w: &mut dyn Write, forecast: &Forecast, days: usize, locale: Locale) -> Result<()> {
+    writeln!(w, "{}", to_json(forecast, days, locale).pretty())?;
+    Ok(())
+}
+
+/// Several forecasts as an array of what `print_json` prints for each.
+pub fn print_json_all(w: &mut dyn Write, forecasts: &[&Forecast], days: usize, locale: Locale) -> Result<()> {
+    let all = forecasts.iter().map(|f| to_json(f, days, locale)).collect();
+    writeln!(w, "{}", Json::Array(all).pretty())?;
+    Ok(())
+}
+
+fn day_to_json(day: &Weather, locale: Locale) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("date".to_string(), day.date.to_json());
+    obj.insert("max_temp_c".to_string(), day.max_temp_c.to_json());
+    obj.insert("min_temp_c".to_string(), day.min_temp_c.to_json());
+    obj.insert("uv_index".to_string(), day.uv_index.to_json());
+    obj.insert("astronomy".to_string(), astronomy_to_json(&day.astronomy));
+    obj.insert("hourly".to_string(), Json::Array(day.hourly.iter()
+                                                     .map(|h| condition_to_json(h, locale))
+                                                     .collect()));
+    Json::Object(obj)
+}
+
+fn astronomy_to_json(astronomy: &Astronomy) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("sunrise".to_string(), astronomy.sunrise.to_json());
+    obj.insert("sunset".to_string(), astronomy.sunset.to_json());
+    obj.insert("moonrise".to_string(), astronomy.moonrise.to_json());
+    obj.insert("moonset".to_string(), astronomy.moonset.to_json());
+    obj.insert("moon_phase".to_string(), astronomy.moon_phase.map(|p| (p * 100.0).round() / 100.0).to_json());
+    obj.insert("moon_illumination".to_string(), astronomy.moon_illumination.to_json());
+    Json::Object(obj)
+}
+
+fn condition_to_json(c: &WeatherCondition, locale: Locale) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("hour".to_string(), c.hour.to_json());
+    obj.insert("condition".to_string(), c.condition.name().to_json());
+    obj.insert("description".to_string(), c.description(locale).to_json());
+    obj.insert("temp_c".to_string(), c.temp_c.to_json());
+    obj.insert("feels_like_c".to_string(), c.feels_like_c.to_json());
+    obj.insert("humidity".to_string(), c.humidity.to_json());
+    obj.insert("cloud_cover".to_string(), c.cloud_cover.to_json());
+    // f32 -> f64 would print 0.4 as 0.4000000059604645
+    obj.insert("precip_mm".to_string(), ((c.precip_mm as f64 * 100.0).round() / 100.0).to_json());
+    obj.insert("chance_of_rain".to_string(), c.chance_of_rain.to_json());
+    obj.insert("visibility_km".to_string(), c.visibility_km.to_json());
+    obj.insert("wind_dir".to_string(), c.wind_dir.to_json());
+    obj.insert("wind_speed_kmph".to_string(), c.wind_speed_kmph.to_json());
+    obj.insert("wind_gust_kmph".to_string(), c.wind_gust_kmph.to_json());
+    obj.insert("pressure_hpa".to_string(), c.pressure_hpa.to_json());
+    obj.insert("dewpoint_c".to_string(), c.dewpoint_c.to_json());
+    Json::Object(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::sample_forecast;
+
+    #[test]
+    fn schema() {
+        let json = to_json(&sample_forecast(), 1, Locale::ZhCn);
+
+        assert_eq!(json["version"], Json::U64(2));
+        assert_eq!(json["location"].as_string(), Some("Beijing, China"));
+        assert_eq!(json["latitude"].as_f64(), Some(39.9075));
+        assert_eq!(json["current"]["condition"].as_string(), Some("partly_cloudy"));
+        assert_eq!(json["current"]["description"].as_string(), Some("局部多云"));
+        assert_eq!(json["current"]["hour"], Json::Null);
+        assert_eq!(json["current"]["pressure_hpa"], Json::I64(1021));
+        assert!(json["current"].find("heat_index_c").is_none());
+        assert_eq!(json["current"]["temp_c"].as_i64(), Some(14));
+
+        let days = json["days"].as_array().unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0]["date"].as_string(), Some("2026-10-18"));
+        assert_eq!(days[0]["astronomy"]["sunrise"].as_string(), Some("06:31"));
+        assert_eq!(days[0]["astronomy"]["moon_illumination"].as_i64(), Some(47));
+        assert_eq!(days[0]["hourly"].as_array().unwrap().len(), 24);
+        assert_eq!(days[0]["hourly"][12]["hour"].as_u64(), Some(12));
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut out = Vec::new();
+        print_json(&mut out, &sample_forecast(), 2, Locale::En).unwrap();
+        let json = Json::from_str(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(json["days"].as_array().unwrap().len(), 2);
+        assert_eq!(json["days"][1]["hourly"][0]["condition"].as_string(), Some("fog"));
+    }
+
+    #[test]
+    fn several_locations() {
+        let forecast = sample_forecast();
+        let mut out = Vec::new();
+        print_json_all(&mut out, &[&forecast, &forecast], 1, Locale::En).unwrap();
+        let json = Json::from_str(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[1]["location"].as_string(), Some("Beijing, China"));
+    }
+}
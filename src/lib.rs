@@ -0,0 +1,46 @@
+//! Weather forecasts for the terminal.
+//!
+//! Fetch a forecast from one of the `backend`s into the provider-neutral
+//! model in `forecast`, then draw it with `render`, or serialize it with
+//! `json`:
+//!
+//! ```no_run
+//! use weather::backend::{self, Query};
+//! use weather::locale::Locale;
+//! use weather::render::{self, Settings};
+//!
+//! let query = Query { location: "Beijing".to_string(), days: 3, locale: Locale::En };
+//! let forecast = backend::by_name("open-meteo", None)?.fetch(&query)?;
+//! let settings = Settings { locale: Locale::En, ..Settings::default() };
+//! render::print_forecast(&mut std::io::stdout(), &forecast, 3, &settings)?;
+//! # Ok::<(), weather::Error>(())
+//! ```
+#![allow(non_snake_case, non_upper_case_globals)]
+#![allow(clippy::redundant_static_lifetimes, clippy::manual_range_patterns)]
+extern crate time;
+extern crate term;
+extern crate rustc_serialize;
+extern crate hyper;
+extern crate toml;
+#[cfg(unix)]
+extern crate libc;
+
+pub mod forecast;
+pub mod astro;
+pub mod backend;
+pub mod config;
+pub mod aliases;
+pub mod location;
+pub mod gazetteer;
+pub mod units;
+pub mod locale;
+pub mod error;
+pub mod color;
+pub mod theme;
+pub mod render;
+pub mod chart;
+pub mod json;
+pub mod template;
+
+pub use error::{Error, Result};
+pub use forecast::{Astronomy, Condition, Forecast, Weather, WeatherCondition};
@@ -0,0 +1,241 @@
+// Translation catalogs for the UI strings and condition descriptions.
+
+use time::Tm;
+
+use forecast::Condition;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Locale {
+    En,
+    ZhCn,
+    ZhTw,
+    Ja,
+    De,
+    Fr,
+    Es,
+    Ru,
+}
+
+pub static LOCALES: [&'static str; 8] = ["en", "zh-cn", "zh-tw", "ja", "de", "fr", "es", "ru"];
+
+/// UI strings.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Text {
+    WeatherFor,
+    Morning,
+    Noon,
+    Evening,
+    Night,
+    Temperature,
+    FeelsLike,
+    Precipitation,
+    ChanceOfRain,
+    Humidity,
+    DewPoint,
+    Clouds,
+}
+
+impl Locale {
+    /// Accepts "zh-cn", "zh_CN", "zh_CN.UTF-8", or just the language,
+    /// e.g. "de". A bare "zh" means Simplified Chinese.
+    pub fn from_name(name: &str) -> Option<Locale> {
+        let name = name.split('.').next().unwrap_or("").to_lowercase().replace('_', "-");
+        match name.as_ref() {
+            "zh-cn" | "zh-sg" | "zh-hans" | "zh" => return Some(Locale::ZhCn),
+            "zh-tw" | "zh-hk" | "zh-hant"        => return Some(Locale::ZhTw),
+            _                                    => (),
+        }
+        match name.split('-').next() {
+            Some("en") | Some("c") | Some("posix") => Some(Locale::En),
+            Some("ja")                             => Some(Locale::Ja),
+            Some("de")                             => Some(Locale::De),
+            Some("fr")                             => Some(Locale::Fr),
+            Some("es")                             => Some(Locale::Es),
+            Some("ru")                             => Some(Locale::Ru),
+            _                                      => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::En   => "en",
+            Locale::ZhCn => "zh-cn",
+            Locale::ZhTw => "zh-tw",
+            Locale::Ja   => "ja",
+            Locale::De   => "de",
+            Locale::Fr   => "fr",
+            Locale::Es   => "es",
+            Locale::Ru   => "ru",
+        }
+    }
+
+    pub fn text(self, text: Text) -> &'static str {
+        let texts: [&'static str; 12] = match self {
+            Locale::En   => ["Weather for:", "Morning", "Noon", "Evening", "Night",
+                             "Temperature", "Feels like", "Precipitation", "Chance of rain",
+                             "Humidity", "Dew point", "Clouds"],
+            Locale::ZhCn => ["天气预报：", "早上", "中午", "傍晚", "夜间",
+                             "温度", "体感温度", "降水量", "降水概率", "湿度", "露点", "云量"],
+            Locale::ZhTw => ["天氣預報：", "早上", "中午", "傍晚", "夜間",
+                             "溫度", "體感溫度", "降水量", "降雨機率", "濕度", "露點", "雲量"],
+            Locale::Ja   => ["天気予報：", "朝", "昼", "夕方", "夜",
+                             "気温", "体感温度", "降水量", "降水確率", "湿度", "露点", "雲量"],
+            Locale::De   => ["Wetter für:", "Morgen", "Mittag", "Abend", "Nacht",
+                             "Temperatur", "Gefühlt", "Niederschlag", "Regenwahrscheinlichkeit",
+                             "Feuchte", "Taupunkt", "Wolken"],
+            Locale::Fr   => ["Météo pour :", "Matin", "Midi", "Soir", "Nuit",
+                             "Température", "Ressenti", "Précipitations", "Risque de pluie",
+                             "Humidité", "Point de rosée", "Nuages"],
+            Locale::Es   => ["El tiempo en:", "Mañana", "Mediodía", "Tarde", "Noche",
+                             "Temperatura", "Sensación", "Precipitación", "Probabilidad de lluvia",
+                             "Humedad", "Punto de rocío", "Nubes"],
+            Locale::Ru   => ["Погода:", "Утро", "День", "Вечер", "Ночь",
+                             "Температура", "Ощущается", "Осадки", "Вероятность дождя",
+                             "Влажность", "Точка росы", "Облака"],
+        };
+        texts[text as usize]
+    }
+
+    /// Short weekday name, 0 is Sunday as in `Tm::tm_wday`.
+    pub fn weekday(self, wday: usize) -> &'static str {
+        let names: [&'static str; 7] = match self {
+            Locale::En   => ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"],
+            Locale::ZhCn => ["周日", "周一", "周二", "周三", "周四", "周五", "周六"],
+            Locale::ZhTw => ["週日", "週一", "週二", "週三", "週四", "週五", "週六"],
+            Locale::Ja   => ["日", "月", "火", "水", "木", "金", "土"],
+            Locale::De   => ["So", "Mo", "Di", "Mi", "Do", "Fr", "Sa"],
+            Locale::Fr   => ["dim", "lun", "mar", "mer", "jeu", "ven", "sam"],
+            Locale::Es   => ["dom", "lun", "mar", "mié", "jue", "vie", "sáb"],
+            Locale::Ru   => ["Вс", "Пн", "Вт", "Ср", "Чт", "Пт", "Сб"],
+        };
+        names[wday % 7]
+    }
+
+    /// Short month name, 0 is January as in `Tm::tm_mon`.
+    pub fn month(self, mon: usize) -> &'static str {
+        let names: [&'static str; 12] = match self {
+            Locale::De => ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun",
+                           "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+            Locale::Fr => ["jan", "fév", "mar", "avr", "mai", "jun",
+                           "jul", "aoû", "sep", "oct", "nov", "déc"],
+            Locale::Es => ["ene", "feb", "mar", "abr", "may", "jun",
+                           "jul", "ago", "sep", "oct", "nov", "dic"],
+            Locale::Ru => ["янв", "фев", "мар", "апр", "мая", "июн",
+                           "июл", "авг", "сен", "окт", "ноя", "дек"],
+            _          => ["Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                           "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+        };
+        names[mon % 12]
+    }
+
+    /// Short date for the day headers, at most 11 columns wide.
+    pub fn format_date(self, tm: &Tm) -> String {
+        let wday = self.weekday(tm.tm_wday as usize);
+        let mon = tm.tm_mon as usize;
+        match self {
+            Locale::En | Locale::De =>
+                format!("{} {:02}. {}", wday, tm.tm_mday, self.month(mon)),
+            Locale::Fr | Locale::Es | Locale::Ru =>
+                format!("{} {} {}", wday, tm.tm_mday, self.month(mon)),
+            Locale::ZhCn | Locale::ZhTw =>
+                format!("{} {}/{}", wday, mon + 1, tm.tm_mday),
+            Locale::Ja =>
+                format!("{}/{} ({})", mon + 1, tm.tm_mday, wday),
+        }
+    }
+
+    pub fn condition(self, cond: Condition) -> &'static str {
+        // in `Condition` order
+        let names: [&'static str; 19] = match self {
+            Locale::En => [
+                "Unknown", "Sunny", "Partly cloudy", "Cloudy", "Overcast", "Fog",
+                "Light showers", "Heavy showers", "Light snow showers", "Heavy snow showers",
+                "Light sleet showers", "Thundery showers", "Thundery heavy rain",
+                "Thundery snow showers", "Light rain", "Heavy rain", "Light snow",
+                "Heavy snow", "Light sleet"],
+            Locale::ZhCn => [
+                "未知", "晴", "局部多云", "多云", "阴", "雾",
+                "小阵雨", "大阵雨", "小阵雪", "大阵雪",
+                "阵雨夹雪", "雷阵雨", "雷暴大雨",
+                "雷阵雪", "小雨", "大雨", "小雪",
+                "大雪", "雨夹雪"],
+            Locale::ZhTw => [
+                "未知", "晴", "局部多雲", "多雲", "陰", "霧",
+                "小陣雨", "大陣雨", "小陣雪", "大陣雪",
+                "陣雨夾雪", "雷陣雨", "雷暴大雨",
+                "雷陣雪", "小雨", "大雨", "小雪",
+                "大雪", "雨夾雪"],
+            Locale::Ja => [
+                "不明", "晴れ", "晴れ時々曇り", "曇り", "厚い曇り", "霧",
+                "弱いにわか雨", "強いにわか雨", "弱いにわか雪", "強いにわか雪",
+                "にわかみぞれ", "雷雨", "雷を伴う大雨",
+                "雷を伴う雪", "小雨", "大雨", "小雪",
+                "大雪", "みぞれ"],
+            Locale::De => [
+                "Unbekannt", "Sonnig", "Teilweise bewölkt", "Bewölkt", "Bedeckt", "Nebel",
+                "Leichte Schauer", "Starke Schauer", "Leichte Schneeschauer", "Starke Schneeschauer",
+                "Schneeregenschauer", "Gewitterschauer", "Gewitter mit Starkregen",
+                "Gewitter mit Schnee", "Leichter Regen", "Starker Regen", "Leichter Schneefall",
+                "Starker Schneefall", "Leichter Schneeregen"],
+            Locale::Fr => [
+                "Inconnu", "Ensoleillé", "Partiellement nuageux", "Nuageux", "Couvert", "Brouillard",
+                "Averses légères", "Fortes averses", "Averses de neige", "Fortes averses de neige",
+                "Averses de neige fondue", "Averses orageuses", "Orage avec fortes pluies",
+                "Orage avec neige", "Pluie légère", "Forte pluie", "Neige légère",
+                "Forte neige", "Neige fondue"],
+            Locale::Es => [
+                "Desconocido", "Soleado", "Parcialmente nublado", "Nublado", "Cubierto", "Niebla",
+                "Chubascos ligeros", "Chubascos fuertes", "Chubascos de nieve", "Chubascos de nieve fuertes",
+                "Chubascos de aguanieve", "Chubascos tormentosos", "Tormenta con lluvia fuerte",
+                "Tormenta con nieve", "Lluvia ligera", "Lluvia fuerte", "Nevada ligera",
+                "Nevada fuerte", "Aguanieve"],
+            Locale::Ru => [
+                "Неизвестно", "Солнечно", "Переменная облачность", "Облачно", "Пасмурно", "Туман",
+                "Небольшой ливень", "Сильный ливень", "Небольшой ливневый снег", "Сильный ливневый снег",
+                "Ливневый мокрый снег", "Гроза с ливнем", "Гроза, сильный дождь",
+                "Гроза со снегом", "Небольшой дождь", "Сильный дождь", "Небольшой снег",
+                "Сильный снег", "Мокрый снег"],
+        };
+        names[cond as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::strptime;
+    use forecast::Condition;
+
+    #[test]
+    fn locale_names() {
+        assert_eq!(Locale::from_name("zh"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_name("zh_CN.UTF-8"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_name("zh-TW"), Some(Locale::ZhTw));
+        assert_eq!(Locale::from_name("de_AT"), Some(Locale::De));
+        assert_eq!(Locale::from_name("C"), Some(Locale::En));
+        assert_eq!(Locale::from_name("xx"), None);
+        for name in LOCALES.iter() {
+            assert_eq!(Locale::from_name(name).map(|l| l.name()), Some(*name));
+        }
+    }
+
+    #[test]
+    fn dates() {
+        let tm = strptime("2015-10-18", "%Y-%m-%d").unwrap();
+        let tm = ::time::at_utc(tm.to_timespec());
+        assert_eq!(Locale::En.format_date(&tm), "Sun 18. Oct");
+        assert_eq!(Locale::De.format_date(&tm), "So 18. Okt");
+        assert_eq!(Locale::Fr.format_date(&tm), "dim 18 oct");
+        assert_eq!(Locale::ZhCn.format_date(&tm), "周日 10/18");
+        assert_eq!(Locale::Ja.format_date(&tm), "10/18 (日)");
+    }
+
+    #[test]
+    fn catalogs() {
+        assert_eq!(Locale::En.condition(Condition::Sunny), "Sunny");
+        assert_eq!(Locale::ZhCn.condition(Condition::LightSleet), "雨夹雪");
+        assert_eq!(Locale::Ru.condition(Condition::Fog), "Туман");
+        assert_eq!(Locale::Ja.text(Text::Night), "夜");
+        assert_eq!(Locale::Fr.text(Text::WeatherFor), "Météo pour :");
+    }
+}
@@ -0,0 +1,181 @@
+// What a location given by the user is, so that each backend can ask its
+// service the right way instead of passing any text through:
+//
+//     52.52,13.405    coordinates, latitude first
+//     10115,DE        a postal code and its country
+//     iata:FRA        an IATA airport code
+//     icao:EDDF       an ICAO airport code
+//     8.8.8.8         an IP address, located by the service
+//     Berlin          anything else is the name of a place
+
+use std::fmt;
+use std::net::IpAddr;
+
+use error::{Error, Result};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Location {
+    Coordinates(f64, f64),
+    Postal { code: String, country: String },
+    Iata(String),
+    Icao(String),
+    Ip(IpAddr),
+    Name(String),
+}
+
+impl Location {
+    /// Recognizes and checks a location, e.g. coordinates out of range or an
+    /// address in a private network are errors.
+    pub fn parse(s: &str) -> Result<Location> {
+        let s = s.trim();
+        let invalid = |what: &str| Err(Error::Usage(format!("invalid {}: {}", what, s)));
+        if s.is_empty() {
+            return Err(Error::Usage("empty location".to_string()));
+        }
+
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            let local = match ip {
+                IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified(),
+                // link-local fe80::/10 and unique local fc00::/7
+                IpAddr::V6(ip) => ip.is_loopback() || ip.is_unspecified()
+                    || ip.segments()[0] & 0xffc0 == 0xfe80 || ip.segments()[0] & 0xfe00 == 0xfc00,
+            };
+            return if local { invalid("IP address, it is not a public one") } else { Ok(Location::Ip(ip)) };
+        }
+
+        let parts = s.split(',').map(|p| p.trim()).collect::<Vec<_>>();
+        if parts.len() == 2 {
+            if let (Ok(lat), Ok(lon)) = (parts[0].parse::<f64>(), parts[1].parse::<f64>()) {
+                return if lat.is_finite() && lon.is_finite() && lat.abs() <= 90.0 && lon.abs() <= 180.0 {
+                    Ok(Location::Coordinates(lat, lon))
+                } else {
+                    invalid("coordinates, latitude is -90 to 90, longitude -180 to 180")
+                };
+            }
+            // a code with a digit and a country, as in "SW1A 1AA,GB" or
+            // "1012 AB,NL"; a word of three letters or more makes "Paris 8, FR"
+            // and "Route 66, Texas" places
+            let (code, country) = (parts[0], parts[1]);
+            let word = code.split(|c: char| !c.is_ascii_alphabetic()).map(|w| w.len()).max().unwrap_or(0);
+            let postal = (2..=10).contains(&code.len())
+                && code.chars().any(|c| c.is_ascii_digit())
+                && code.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-')
+                && word < 3
+                && country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic());
+            if postal {
+                return Ok(Location::Postal { code: code.to_uppercase(), country: country.to_uppercase() });
+            }
+        }
+
+        // only with the prefix, OSLO and NYC are places
+        let prefix = s.get(..5).map(|p| p.to_ascii_lowercase());
+        let code = s.get(5..).unwrap_or("").trim();
+        let letters = |n: usize| code.len() == n && code.chars().all(|c| c.is_ascii_alphabetic());
+        match prefix.as_ref().map(|p| &p[..]) {
+            Some("iata:") if letters(3) => Ok(Location::Iata(code.to_uppercase())),
+            Some("iata:")               => invalid("IATA airport code, expected e.g. iata:FRA"),
+            Some("icao:") if letters(4) => Ok(Location::Icao(code.to_uppercase())),
+            Some("icao:")               => invalid("ICAO airport code, expected e.g. icao:EDDF"),
+            _                           => Ok(Location::Name(s.to_string())),
+        }
+    }
+
+    /// What the location is, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Location::Coordinates(..) => "coordinates",
+            Location::Postal { .. }   => "postal codes",
+            Location::Iata(_)         => "IATA airport codes",
+            Location::Icao(_)         => "ICAO airport codes",
+            Location::Ip(_)           => "IP addresses",
+            Location::Name(_)         => "place names",
+        }
+    }
+
+    /// The error of a backend whose service can't look this kind of
+    /// location up.
+    pub fn unsupported(&self, backend: &str) -> Error {
+        Error::Usage(format!("the {} backend can't look up {}", backend, self.kind()))
+    }
+}
+
+/// The normalized form, which `parse` reads back.
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Location::Coordinates(lat, lon)          => write!(f, "{},{}", lat, lon),
+            Location::Postal { ref code, ref country } => write!(f, "{},{}", code, country),
+            Location::Iata(ref code)                 => write!(f, "iata:{}", code),
+            Location::Icao(ref code)                 => write!(f, "icao:{}", code),
+            Location::Ip(ip)                         => write!(f, "{}", ip),
+            Location::Name(ref name)                 => write!(f, "{}", name),
+        }
+    }
+}
+
+/// "Berlin, Land Berlin, Germany"; the region is left out when it's the
+/// place itself, as for "Beijing, China".
+pub fn display_name(name: &str, region: Option<&str>, country: Option<&str>) -> String {
+    let mut parts = vec![name];
+    parts.extend(region.filter(|r| !r.is_empty() && *r != name));
+    parts.extend(country.filter(|c| !c.is_empty()));
+    parts.join(", ")
+}
+
+/// "52.52°N 13.41°E"
+pub fn format_coordinates(lat: f64, lon: f64) -> String {
+    format!("{:.2}°{} {:.2}°{}", lat.abs(), if lat < 0.0 { 'S' } else { 'N' },
+            lon.abs(), if lon < 0.0 { 'W' } else { 'E' })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        let parse = |s| Location::parse(s).unwrap();
+        assert_eq!(parse(" 52.52, 13.405 "), Location::Coordinates(52.52, 13.405));
+        assert_eq!(parse("-33.87,151.21"), Location::Coordinates(-33.87, 151.21));
+        assert_eq!(parse("10115,de"), Location::Postal { code: "10115".to_string(), country: "DE".to_string() });
+        assert_eq!(parse("sw1a 1aa, gb").to_string(), "SW1A 1AA,GB");
+        assert_eq!(parse("K1A 0B1,ca").to_string(), "K1A 0B1,CA");
+        assert_eq!(parse("1012 ab,NL").to_string(), "1012 AB,NL");
+        assert_eq!(parse("02-495, pl").to_string(), "02-495,PL");
+        assert_eq!(parse("Paris 8, FR"), Location::Name("Paris 8, FR".to_string()));
+        assert_eq!(parse("Route 66, Texas"), Location::Name("Route 66, Texas".to_string()));
+        assert_eq!(parse("10115,Germany"), Location::Name("10115,Germany".to_string()));
+        assert_eq!(parse("iata:fra"), Location::Iata("FRA".to_string()));
+        assert_eq!(parse("IATA: FRA").to_string(), "iata:FRA");
+        assert_eq!(parse("icao:eddf"), Location::Icao("EDDF".to_string()));
+        assert_eq!(parse("ICAO:EDDF").to_string(), "icao:EDDF");
+        assert_eq!(parse("OSLO"), Location::Name("OSLO".to_string()));
+        assert_eq!(parse("NYC"), Location::Name("NYC".to_string()));
+        assert_eq!(parse("8.8.8.8").to_string(), "8.8.8.8");
+        assert_eq!(parse("2001:4860:4860::8888").kind(), "IP addresses");
+        assert_eq!(parse("Rom"), Location::Name("Rom".to_string()));
+        assert_eq!(parse("Paris, FR"), Location::Name("Paris, FR".to_string()));
+        assert_eq!(parse("New York"), Location::Name("New York".to_string()));
+        assert_eq!(parse("北京市"), Location::Name("北京市".to_string()));
+
+        assert!(Location::parse("91,0").is_err());
+        assert!(Location::parse("0,180.5").is_err());
+        assert!(Location::parse("iata:EDDF").is_err());
+        assert!(Location::parse("icao:FRA").is_err());
+        assert!(Location::parse("192.168.1.1").is_err());
+        assert!(Location::parse("::1").is_err());
+        assert!(Location::parse("fe80::1").is_err());
+        assert!(Location::parse("fd12:3456::1").is_err());
+        assert!(Location::parse("fc00::1").is_err());
+        assert!(Location::parse(" ").is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(display_name("Beijing", Some("Beijing"), Some("China")), "Beijing, China");
+        assert_eq!(display_name("Berlin", Some("Land Berlin"), Some("Germany")), "Berlin, Land Berlin, Germany");
+        assert_eq!(display_name("Nowhere", None, Some("")), "Nowhere");
+        assert_eq!(format_coordinates(-33.868, 151.2093), "33.87°S 151.21°E");
+        assert_eq!(format_coordinates(40.7, -74.0), "40.70°N 74.00°W");
+    }
+}
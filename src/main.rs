@@ -1,548 +1,395 @@
- #![allow(non_snake_case, non_upper_case_globals)]
-extern crate time;
-extern crate term;
-extern crate rustc_serialize;
-extern crate hyper;
 extern crate getopts;
+extern crate weather;
 
 use std::env;
-use std::io::Error;
+use std::io;
 use std::io::prelude::*;
-use std::iter;
-use std::iter::FromIterator;
+use std::process;
+use std::rc::Rc;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use time::{strftime, strptime};
-use rustc_serialize::json;
-use hyper::{Client, Url};
 use getopts::Options;
 
-static BASE_URL: &'static str = "http://api.worldweatheronline.com/free/v2/weather.ashx";
-static KEY: &'static str = "a444bbde1001764c4634bc7079a7c";
-static CELL_WIDTH: usize = 30;
-// configuration
-static mut USE_ZH: bool = false;
+use weather::{backend, chart, color, json, locale, location, theme, units, render};
+use weather::aliases::Aliases;
+use weather::color::{ColorDepth, ColorMode};
+use weather::backend::Query;
+use weather::chart::ChartStyle;
+use weather::config::Config;
+use weather::gazetteer::Gazetteer;
+use weather::units::UnitSystem;
+use weather::locale::Locale;
+use weather::location::Location;
+use weather::render::{HasTerminalDisplayLength, Settings};
+use weather::template::Template;
+use weather::theme::Theme;
+use weather::{Error, Result};
 
-pub trait HasTerminalDisplayLength {
-    fn len_on_term(&self) -> usize;
-    fn fit_to_term_len(&self, new_len: usize) -> String;
+fn print_usage(program: &str, opts: &Options) {
+    let brief = format!("Usage: {0} [options] [CITY...]\n       \
+                         {0} alias [list | add NAME LOCATION | remove NAME]\n       \
+                         {0} search NAME", program);
+    print!("{}", opts.usage(&brief));
 }
 
-impl HasTerminalDisplayLength for String {
-    fn len_on_term(&self) -> usize {
-        let mut ret = 0usize;
-        let mut wait_for_color_mark_ends = false;
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
 
-        for c in self.chars() {
-            if c == '\u{1b}' && !wait_for_color_mark_ends {
-                wait_for_color_mark_ends = true;
-            } else if c == 'm' && wait_for_color_mark_ends {
-                wait_for_color_mark_ends = false;
-            } else {
-                if !wait_for_color_mark_ends {
-                    match c {
-                        // http://blog.oasisfeng.com/2006/10/19/full-cjk-unicode-range/
-                        '\u{3400}'...'\u{4DB5}' | '\u{4E00}'...'\u{9FA5}' | '\u{9FA6}'...'\u{9FBB}' |
-                        '\u{F900}'...'\u{FA2D}' | '\u{FA30}'...'\u{FA6A}' | '\u{FA70}'...'\u{FAD9}' |
-                        '\u{20000}'...'\u{2A6D6}' | '\u{2F800}'...'\u{2FA1D}' |
-                        '\u{FF00}'...'\u{FFEF}' | '\u{2E80}'...'\u{2EFF}' |
-                        '\u{3000}'...'\u{303F}' | '\u{31C0}'...'\u{31EF}' =>
-                            ret += 2,
-                        _ =>
-                            ret += 1
-                    }
-                }
+    if let Err(e) = run(&args) {
+        // e.g. piped into `head`
+        if let Error::Io(ref err) = e {
+            if err.kind() == io::ErrorKind::BrokenPipe {
+                return;
             }
         }
-        ret
-    }
-
-    fn fit_to_term_len(&self, new_len: usize) -> String {
-        // NOTE: .len is bytes len(); str Index is in bytes
-        if self.len_on_term() < new_len  {
-            let actual_char_len = self.chars().count() + new_len - self.len_on_term();
-            String::from_iter(self.chars().chain(iter::repeat(' ')).take(actual_char_len))
-        } else {
-            let actual_len = self.len() + new_len - self.len_on_term();
-            self[..actual_len].to_string()
-        }
-    }
-}
-
-fn wind_dir_to_icon(code: &str) -> &'static str {
-    match code {
-        "N"   => "\u{1b}[1m↓\u{1b}[0m",
-	      "NNE" => "\u{1b}[1m↓\u{1b}[0m",
-	      "NE"  => "\u{1b}[1m↙\u{1b}[0m",
-	      "ENE" => "\u{1b}[1m↙\u{1b}[0m",
-	      "E"   => "\u{1b}[1m←\u{1b}[0m",
-	      "ESE" => "\u{1b}[1m←\u{1b}[0m",
-	      "SE"  => "\u{1b}[1m↖\u{1b}[0m",
-	      "SSE" => "\u{1b}[1m↖\u{1b}[0m",
-	      "S"   => "\u{1b}[1m↑\u{1b}[0m",
-	      "SSW" => "\u{1b}[1m↑\u{1b}[0m",
-	      "SW"  => "\u{1b}[1m↗\u{1b}[0m",
-	      "WSW" => "\u{1b}[1m↗\u{1b}[0m",
-	      "W"   => "\u{1b}[1m→\u{1b}[0m",
-	      "WNW" => "\u{1b}[1m→\u{1b}[0m",
-	      "NW"  => "\u{1b}[1m↘\u{1b}[0m",
-	      "NNW" => "\u{1b}[1m↘\u{1b}[0m",
-        _     => " "
+        let _ = io::stdout().flush();
+        eprintln!("{}: {}", args[0], e);
+        process::exit(e.exit_code());
     }
 }
 
-fn code_to_icon(code: i32) -> [&'static str; 5] {
-    match code {
-        113 => iconSunny,
-        116 => iconPartlyCloudy,
-        119 => iconCloudy,
-        122 => iconVeryCloudy,
-        143 => iconFog,
-        176 => iconLightShowers,
-        179 => iconLightSleetShowers,
-        182 => iconLightSleet,
-        185 => iconLightSleet,
-        200 => iconThunderyShowers,
-        227 => iconLightSnow,
-        230 => iconHeavySnow,
-        248 => iconFog,
-        260 => iconFog,
-        263 => iconLightShowers,
-        266 => iconLightRain,
-        281 => iconLightSleet,
-        284 => iconLightSleet,
-        293 => iconLightRain,
-        296 => iconLightRain,
-        299 => iconHeavyShowers,
-        302 => iconHeavyRain,
-        305 => iconHeavyShowers,
-        308 => iconHeavyRain,
-        311 => iconLightSleet,
-        314 => iconLightSleet,
-        317 => iconLightSleet,
-        320 => iconLightSnow,
-        323 => iconLightSnowShowers,
-        326 => iconLightSnowShowers,
-        329 => iconHeavySnow,
-        332 => iconHeavySnow,
-        335 => iconHeavySnowShowers,
-        338 => iconHeavySnow,
-        350 => iconLightSleet,
-        353 => iconLightShowers,
-        356 => iconHeavyShowers,
-        359 => iconHeavyRain,
-        362 => iconLightSleetShowers,
-        365 => iconLightSleetShowers,
-        368 => iconLightSnowShowers,
-        371 => iconHeavySnowShowers,
-        374 => iconLightSleetShowers,
-        377 => iconLightSleet,
-        386 => iconThunderyShowers,
-        389 => iconThunderyHeavyRain,
-        392 => iconThunderySnowShowers,
-        395 => iconHeavySnowShowers, // ThunderyHeavySnow
-        _   => iconUnknown
+fn run(args: &[String]) -> Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
 
-    }
-}
-
-static iconUnknown: [&'static str; 5] = [
-		"    .-.      ",
-		"     __)     ",
-		"    (        ",
-		"     `-’     ",
-		"      •      "];
-static iconSunny: [&'static str; 5] = [
-		"\u{1b}[38;5;226m    \\   /    \u{1b}[0m",
-		"\u{1b}[38;5;226m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;226m  ― (   ) ―  \u{1b}[0m",
-		"\u{1b}[38;5;226m     `-’     \u{1b}[0m",
-		"\u{1b}[38;5;226m    /   \\    \u{1b}[0m"];
-static iconPartlyCloudy: [&'static str; 5] = [
-		"\u{1b}[38;5;226m   \\  /\u{1b}[0m      ",
-		"\u{1b}[38;5;226m _ /\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m   \\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
-		"             "];
-static iconCloudy: [&'static str; 5] = [
-		"             ",
-		"\u{1b}[38;5;250m     .--.    \u{1b}[0m",
-		"\u{1b}[38;5;250m  .-(    ).  \u{1b}[0m",
-		"\u{1b}[38;5;250m (___.__)__) \u{1b}[0m",
-		"             "];
-static iconVeryCloudy: [&'static str; 5] = [
-		"             ",
-		"\u{1b}[38;5;240;1m     .--.    \u{1b}[0m",
-		"\u{1b}[38;5;240;1m  .-(    ).  \u{1b}[0m",
-		"\u{1b}[38;5;240;1m (___.__)__) \u{1b}[0m",
-		"             "];
-static iconLightShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;111m     ‘ ‘ ‘ ‘ \u{1b}[0m",
-		"\u{1b}[38;5;111m    ‘ ‘ ‘ ‘  \u{1b}[0m"];
-static iconHeavyShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;240;1m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;240;1m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;240;1m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;21;1m   ‚‘‚‘‚‘‚‘  \u{1b}[0m",
-		"\u{1b}[38;5;21;1m   ‚’‚’‚’‚’  \u{1b}[0m"];
-static iconLightSnowShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;255m     *  *  * \u{1b}[0m",
-		"\u{1b}[38;5;255m    *  *  *  \u{1b}[0m"];
-static iconHeavySnowShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;240;1m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;240;1m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;240;1m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;255;1m    * * * *  \u{1b}[0m",
-		"\u{1b}[38;5;255;1m   * * * *   \u{1b}[0m"];
-static iconLightSleetShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;111m     ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m* \u{1b}[0m",
-		"\u{1b}[38;5;255m    *\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘  \u{1b}[0m"];
-static iconThunderyShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;228;5m    ⚡\u{1b}[38;5;111;25m‘ ‘\u{1b}[38;5;228;5m⚡\u{1b}[38;5;111;25m‘ ‘ \u{1b}[0m",
-		"\u{1b}[38;5;111m    ‘ ‘ ‘ ‘  \u{1b}[0m"];
-static iconThunderyHeavyRain: [&'static str; 5] = [
-		"\u{1b}[38;5;240;1m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;240;1m    (   ).   \u{1b}[0m",
-		"\u{1b}[38;5;240;1m   (___(__)  \u{1b}[0m",
-		"\u{1b}[38;5;21;1m  ‚‘\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m‘‚\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m‚‘   \u{1b}[0m",
-		"\u{1b}[38;5;21;1m  ‚’‚’\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m’‚’   \u{1b}[0m"];
-static iconThunderySnowShowers: [&'static str; 5] = [
-		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
-		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
-		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
-		"\u{1b}[38;5;255m     *\u{1b}[38;5;228;5m⚡\u{1b}[38;5;255;25m *\u{1b}[38;5;228;5m⚡\u{1b}[38;5;255;25m * \u{1b}[0m",
-		"\u{1b}[38;5;255m    *  *  *  \u{1b}[0m"];
-static iconLightRain: [&'static str; 5] = [
-		"\u{1b}[38;5;250m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;250m    (   ).   \u{1b}[0m",
-		"\u{1b}[38;5;250m   (___(__)  \u{1b}[0m",
-		"\u{1b}[38;5;111m    ‘ ‘ ‘ ‘  \u{1b}[0m",
-		"\u{1b}[38;5;111m   ‘ ‘ ‘ ‘   \u{1b}[0m"];
-static iconHeavyRain: [&'static str; 5] = [
-		"\u{1b}[38;5;240;1m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;240;1m    (   ).   \u{1b}[0m",
-		"\u{1b}[38;5;240;1m   (___(__)  \u{1b}[0m",
-		"\u{1b}[38;5;21;1m  ‚‘‚‘‚‘‚‘   \u{1b}[0m",
-		"\u{1b}[38;5;21;1m  ‚’‚’‚’‚’   \u{1b}[0m"];
-static iconLightSnow: [&'static str; 5] = [
-		"\u{1b}[38;5;250m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;250m    (   ).   \u{1b}[0m",
-		"\u{1b}[38;5;250m   (___(__)  \u{1b}[0m",
-		"\u{1b}[38;5;255m    *  *  *  \u{1b}[0m",
-		"\u{1b}[38;5;255m   *  *  *   \u{1b}[0m"];
-static iconHeavySnow: [&'static str; 5] = [
-		"\u{1b}[38;5;240;1m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;240;1m    (   ).   \u{1b}[0m",
-		"\u{1b}[38;5;240;1m   (___(__)  \u{1b}[0m",
-		"\u{1b}[38;5;255;1m   * * * *   \u{1b}[0m",
-		"\u{1b}[38;5;255;1m  * * * *    \u{1b}[0m"];
-static iconLightSleet: [&'static str; 5] = [
-		"\u{1b}[38;5;250m     .-.     \u{1b}[0m",
-		"\u{1b}[38;5;250m    (   ).   \u{1b}[0m",
-		"\u{1b}[38;5;250m   (___(__)  \u{1b}[0m",
-		"\u{1b}[38;5;111m    ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m*  \u{1b}[0m",
-		"\u{1b}[38;5;255m   *\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘   \u{1b}[0m"];
-static iconFog: [&'static str; 5] = [
-		"             ",
-		"\u{1b}[38;5;251m _ - _ - _ - \u{1b}[0m",
-		"\u{1b}[38;5;251m  _ - _ - _  \u{1b}[0m",
-		"\u{1b}[38;5;251m _ - _ - _ - \u{1b}[0m",
-		"             "];
-
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct DataWrapper  {
-    data: Data
-}
+    let mut opts = Options::new();
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct Data  {
-    current_condition: Vec<WeatherCondition>,
-    request: Vec<Request>,
-    weather: Vec<Weather>
-}
+    opts.optflag("h", "help", "print help message")
+        .optflag("",  "zh",   "use zh-cn locale, same as --locale zh-cn")
+        .optopt("",   "locale", &format!("language, one of: {}", locale::LOCALES.join(", ")), "LOCALE")
+        .optopt("d",  "days", "number of days in output", "DAYS")
+        .optopt("b",  "backend", &format!("weather service, one of: {}", backend::BACKENDS.join(", ")), "NAME")
+        .optopt("u",  "units", &format!("unit system, one of: {}", units::UNIT_SYSTEMS.join(", ")), "UNITS")
+        .optopt("k",  "key", "API key for the weather service", "KEY")
+        .optmulti("l", "location", "a location, can be given several times", "CITY")
+        .optopt("f",  "format", "output format, one of: text, json, or a template like '%c %t %w'", "FORMAT")
+        .optopt("",   "color", &format!("when to use colours, one of: {}", color::COLOR_MODES.join(", ")), "WHEN")
+        .optopt("",   "color-depth", &format!("colours the terminal can show, one of: {}; detected by default",
+                                              color::COLOR_DEPTHS.join(", ")), "DEPTH")
+        .optflag("",  "hourly", "show every hour instead of the day tables")
+        .optflagopt("", "chart", &format!("show charts instead of the day tables, STYLE one of: {}; detected by \
+                                           default", chart::CHART_STYLES.join(", ")), "STYLE")
+        .optflag("",  "oneline", "one line per day, for prompts and status bars")
+        .optflagopt("", "compare", "the locations side by side, their current conditions or those of DAY, 0 for \
+                                    today, 1 for tomorrow, ...", "DAY")
+        .optflag("",  "details", "also show humidity, cloud cover, UV index and pressure")
+        .optopt("s",  "slots", "hours shown for each day, default 9,12,18,21", "HOURS")
+        .optopt("w",  "width", "output width in columns, detected by default", "COLUMNS")
+        .optopt("",   "theme", &format!("colour theme, one of: {}, or a theme file", theme::THEMES.join(", ")), "THEME")
+        .optopt("c",  "config", "config file, default $XDG_CONFIG_HOME/weather/config.toml", "FILE");
+
+    let matches = opts.parse(&args[1..]).map_err(|f| Error::Usage(f.to_string()))?;
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct WeatherCondition {
-    cloudcover: i32,
-    FeelsLikeC: i32,
-    humidity: i32,
-    precipMM: f32,
-    weatherCode: i32,
-    // FIXME: :(
-    temp_C: Option<i32>,
-    tempC: Option<i32>,
-    time: Option<String>,
-    chanceofrain: Option<i32>,
-    observation_time: Option<String>,
-    visibility: i32,
-    weatherDesc: Vec<ValueWrapper>,
-    lang_zh: Vec<ValueWrapper>,
-    winddir16Point: String,
-    windspeedKmph: i32,
-    WindGustKmph: Option<i32>
-}
+    if matches.opt_present("h") {
+        print_usage(&args[0], &opts);
+        return Ok(());
+    }
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct ValueWrapper {
-    value: String
-}
+    let config = match matches.opt_str("config") {
+        Some(path) => Config::load(Path::new(&path))?,
+        None       => Config::load_default()?,
+    };
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct Request {
-    query: String,
-    // FIXME: waiting rust-nighty to enable serde compiler ext
-    // type_: String
-}
+    let aliases_path = Aliases::default_path();
+    let aliases = match aliases_path {
+        Some(ref path) => Aliases::load(path)?,
+        None           => Aliases::default(),
+    };
+    if matches.free.first().map(|a| &a[..]) == Some("alias") {
+        return run_alias(&mut stdout, &matches.free[1..], aliases, aliases_path);
+    }
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct Weather {
-    astronomy: Vec<Astronomy>,
-    date: String,
-    hourly: Vec<WeatherCondition>,
-    maxtempC: i32,
-    mintempC: i32,
-    uvIndex: i32
-}
+    let locale_name = if matches.opt_present("zh") {
+        "zh-cn".to_string()
+    } else {
+        matches.opt_str("locale")
+            .or(config.locale.clone())
+            .unwrap_or_else(|| "en".to_string())
+    };
+    let locale = Locale::from_name(&locale_name)
+        .ok_or_else(|| Error::Usage(format!("unknown locale: {}", locale_name)))?;
 
-impl Weather {
-    fn print_day(&self, w: &mut Write) -> Result<(), Error> {
-        let local_date = strptime(&self.date, "%Y-%m-%d").unwrap().to_local();
-        let date_fmt = "┤ ".to_string() + strftime("%a %d. %b", &local_date).as_ref().unwrap() + " ├";
-        try!(writeln!(w, "                                                       ┌─────────────┐                                                       "));
-	try!(writeln!(w, "┌──────────────────────────────┬───────────────────────{}───────────────────────┬──────────────────────────────┐", date_fmt));
-        try!(writeln!(w, "│           Morning            │             Noon      └──────┬──────┘    Evening            │            Night             │"));
-        try!(writeln!(w, "├──────────────────────────────┼──────────────────────────────┼──────────────────────────────┼──────────────────────────────┤"));
-        for line in self.format_day().iter() {
-            try!(writeln!(w, "{}", line));
-        }
-        try!(writeln!(w, "└──────────────────────────────┴──────────────────────────────┴──────────────────────────────┴──────────────────────────────┘"));
-        Ok(())
+    // a configured gazetteer must exist, the default one may
+    let gazetteer_path = config.gazetteer.as_ref().map(PathBuf::from).or_else(Gazetteer::default_path);
+    if matches.free.first().map(|a| &a[..]) == Some("search") {
+        return run_search(&mut stdout, &matches.free[1..], gazetteer_path, locale);
     }
 
-    fn format_day(&self) -> Vec<String> {
-        let mut ret = Vec::with_capacity(5);
-        ret.extend(iter::repeat("|".to_string()).take(5));
-
-        for h in self.hourly.iter() {
-            let time = h.time.clone().unwrap();
-            match time.as_ref() {
-                "0" | "100" | "200" | "300" | "400" | "500" |
-                "600" | "700" | "1400" | "1500" | "1600" | "2300" =>
-                    continue,
-                _                                                 => {
-                    let cond_desc = h.format();
-
-                    for (i, line) in ret.iter_mut().enumerate() {
-                        let orig = line.clone();
-                        *line = orig + &cond_desc[i] + "|";
-                    }
-                }
-            }
-        }
-        ret
-    }
-}
+    let num_of_days: usize = match matches.opt_str("days") {
+        // like `days` in the config file
+        Some(s) => usize::from_str(&s).ok().filter(|&n| n > 0)
+            .ok_or_else(|| Error::Usage(format!("days must be a positive number: {}", s)))?,
+        None    => config.days.unwrap_or(3),
+    };
 
+    let units_name = matches.opt_str("units")
+        .or(config.units.clone())
+        .unwrap_or_else(|| "metric".to_string());
+    let units = UnitSystem::from_name(&units_name)
+        .ok_or_else(|| Error::Usage(format!("unknown unit system: {}", units_name)))?;
+
+    let color_name = matches.opt_str("color")
+        .or(config.color.clone())
+        .unwrap_or_else(|| "auto".to_string());
+    let color_mode = ColorMode::from_name(&color_name)
+        .ok_or_else(|| Error::Usage(format!("unknown colour mode: {}", color_name)))?;
+
+    let depth = match matches.opt_str("color-depth").or(config.color_depth.clone()) {
+        Some(name) => ColorDepth::from_name(&name)
+            .ok_or_else(|| Error::Usage(format!("unknown colour depth: {}", name)))?,
+        None       => ColorDepth::detect(),
+    };
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
-pub struct Astronomy {
-    moonrise: String,
-    moonset: String,
-    sunrise: String,
-    sunset: String
-}
+    let theme = match matches.opt_str("theme").or(config.theme.clone()) {
+        Some(name) => Theme::find(&name)?,
+        None       => Theme::default(),
+    };
 
+    let width = match matches.opt_str("width") {
+        Some(s) => usize::from_str(&s).map_err(|_| Error::Usage(format!("width must be a number: {}", s)))?,
+        None    => render::terminal_width().unwrap_or(render::WIDE),
+    };
 
-fn colorized_temp(temp: i32) -> String {
-    let col = match temp {
-        -15 | -14 | -13 => 27,
-	      -12 | -11 | -10 => 33,
-	      -9 | -8 | -7    => 39,
-	      -6 | -5 | -4    => 45,
-	      -3 | -2 | -1    => 51,
-	      0 | 1           => 50,
-	      2 | 3           => 49,
-	      4 | 5           => 48,
-	      6 | 7           => 47,
-	      8 | 9           => 46,
-	      10 | 11 | 12    => 82,
-	      13 | 14 | 15    => 118,
-	      16 | 17 | 18    => 154,
-	      19 | 20 | 21    => 190,
-	      22 | 23 | 24    => 226,
-	      25 | 26 | 27    => 220,
-	      28 | 29 | 30    => 214,
-	      31 | 32 | 33    => 208,
-	      34 | 35 | 36    => 202,
-        _ if temp > 0   => 196,
-        _               => 21
+    let slots = match matches.opt_str("slots") {
+        Some(s) => render::parse_slots(&s)
+            .ok_or_else(|| Error::Usage(format!("slots must be hours from 0 to 23, e.g. 6,12,18: {}", s)))?,
+        None    => config.slots.clone().unwrap_or_else(|| render::DEFAULT_SLOTS.to_vec()),
     };
-    format!("\u{1b}[38;5;{:03}m{}\u{1b}[0m", col, temp)
-}
 
-fn colorized_wind(spd: i32) -> String {
-    let col = match spd {
-        1 | 2 | 3         => 82,
-        4 | 5 | 6         => 118,
-        7 | 8 | 9         => 154,
-        10 | 11 | 12      => 190,
-        13 | 14 | 15      => 226,
-        16 | 17 | 18 | 19 => 220,
-        20 | 21 | 22 | 23 => 214,
-        24 | 25 | 26 | 27 => 208,
-        28 | 29 | 30 | 31 => 202,
-        _ if spd > 0      => 196,
-        _                 => 46
+    let format = matches.opt_str("format").unwrap_or_else(|| "text".to_string());
+    let template = match format.as_ref() {
+        "text" | "json"      => None,
+        f if f.contains('%') => Some(Template::parse(f)?),
+        f                    => return Err(Error::Usage(format!("unknown format: {}", f))),
     };
-    format!("\u{1b}[38;5;{:03}m{}\u{1b}[0m", col, spd)
-}
 
+    let chart_style = match matches.opt_default("chart", "") {
+        Some(ref name) if name.is_empty() => Some(ChartStyle::detect()),
+        Some(name)                        => Some(ChartStyle::from_name(&name)
+            .ok_or_else(|| Error::Usage(format!("unknown chart style: {}", name)))?),
+        None                              => None,
+    };
+    // Some(None) for the current conditions
+    let compare = match matches.opt_default("compare", "") {
+        Some(ref day) if day.is_empty() => Some(None),
+        Some(day)                       => Some(Some(usize::from_str(&day)
+            .map_err(|_| Error::Usage(format!("the day to compare must be a number: {}", day)))?)),
+        None                            => None,
+    };
+    // json and templates are views of their own
+    let views = ["hourly", "chart", "oneline", "compare"].iter().filter(|v| matches.opt_present(v)).count();
+    if views + (format != "text") as usize > 1 {
+        return Err(Error::Usage("only one of --hourly, --chart, --oneline, --compare, --format json and a --format \
+                                 template can be given".to_string()));
+    }
 
-impl WeatherCondition {
-    fn temp_in_C(&self) -> i32 {
-        self.tempC.or(self.temp_C).unwrap()
+    // -l Beijing -l "New York", Beijing "New York", or "Beijing; New York"
+    let mut locations = matches.opt_strs("location");
+    locations.extend(matches.free.iter()
+                         .flat_map(|arg| arg.split(';'))
+                         .map(|l| l.trim().to_string())
+                         .filter(|l| !l.is_empty()));
+    if locations.is_empty() {
+        locations = config.locations.clone()
+            .or_else(|| config.location.clone().map(|l| vec![l]))
+            .unwrap_or_else(|| vec!["Beijing".to_string()]);
     }
 
-    fn format_visibility(&self) -> String {
-        format!("{} {}", self.visibility, "km").to_string()
+    // aliases are shown by their name
+    let mut names = locations.iter().map(|l| aliases.get(l).map(|_| l.clone())).collect::<Vec<_>>();
+    // checked before asking for any of them
+    let mut resolved = Vec::new();
+    for l in &locations {
+        resolved.push(Location::parse(aliases.get(l).unwrap_or(l))?);
     }
 
-    fn format_wind(&self) -> String {
-        let windGustKmph = self.WindGustKmph.unwrap_or(0);
-        if windGustKmph > self.windspeedKmph {
-            format!("{} {} - {} {}      ",
-                    wind_dir_to_icon(self.winddir16Point.as_ref()),
-                    colorized_wind(self.windspeedKmph),
-                    colorized_wind(windGustKmph),
-                    "km/h").to_string()
-        } else {
-            format!("{} {} {}      ",
-                    wind_dir_to_icon(self.winddir16Point.as_ref()),
-                    colorized_wind(self.windspeedKmph),
-                    "km/h").to_string()
+    // place names the gazetteer knows exactly are sent as coordinates, and
+    // shown by the gazetteer's name; only the lines naming them are read
+    let place_names = resolved.iter().filter_map(|l| match *l {
+        Location::Name(ref n) => Some(&n[..]),
+        _                     => None,
+    }).collect::<Vec<_>>();
+    let gazetteer = match gazetteer_path {
+        Some(ref path) if !place_names.is_empty() && (config.gazetteer.is_some() || path.exists()) =>
+            Some(Gazetteer::load_for(path, &place_names)?),
+        _ => None,
+    };
+    for (location, name) in resolved.iter_mut().zip(names.iter_mut()) {
+        let place = match *location {
+            Location::Name(ref n) => gazetteer.as_ref().and_then(|g| g.lookup(n))
+                .map(|place| (place.latitude, place.longitude, place.display_name(locale))),
+            _                     => None,
+        };
+        if let Some((lat, lon, display_name)) = place {
+            *location = Location::Coordinates(lat, lon);
+            name.get_or_insert(display_name);
         }
     }
 
-    fn format_temp(&self) -> String {
-        if self.FeelsLikeC < self.temp_in_C() {
-            format!("{} - {} °C         ",
-                    colorized_temp(self.FeelsLikeC),
-                    colorized_temp(self.temp_in_C()))
-        } else if self.FeelsLikeC > self.temp_in_C() {
-            format!("{} - {} °C         ",
-                    colorized_temp(self.temp_in_C()),
-                    colorized_temp(self.FeelsLikeC))
-        } else {
-            format!("{} °C             ",
-                    colorized_temp(self.FeelsLikeC))
+    let queries = resolved.iter().map(|location| Query {
+        location: location.to_string(),
+        // enough for the day to compare
+        days: match compare {
+            Some(Some(day)) => num_of_days.max(day + 1),
+            _               => num_of_days,
+        },
+        locale,
+    }).collect::<Vec<_>>();
+
+    let backend_name = matches.opt_str("backend")
+        .or(config.backend)
+        .unwrap_or_else(|| backend::BACKENDS[0].to_string());
+    let backend = backend::by_name(&backend_name, matches.opt_str("key").or(config.key))?;
+    let mut forecasts = Vec::new();
+    let mut errors = Vec::new();
+    for (result, name) in backend::fetch_all(&*backend, &queries).into_iter().zip(names) {
+        match result {
+            Ok(mut forecast) => {
+                if let Some(name) = name {
+                    forecast.location = name;
+                }
+                forecasts.push(forecast);
+            }
+            Err(e)           => errors.push(e),
         }
     }
+    let forecasts = forecasts.iter().collect::<Vec<_>>();
+
+    let settings = Settings {
+        units,
+        locale,
+        color: color_mode.enabled(),
+        depth,
+        theme: Rc::new(theme),
+        width,
+        slots,
+        details: matches.opt_present("details") || config.details.unwrap_or(false),
+    };
 
-    fn format_rain(&self) -> String {
-        match self.chanceofrain {
-            Some(ratio) =>
-                format!("{:.1} {} | {}%        ", self.precipMM, "mm", ratio),
-            None =>
-                format!("{:.1} {}              ", self.precipMM, "mm")
+    if forecasts.is_empty() {
+        // nothing to print
+    } else if format == "json" && queries.len() == 1 {
+        json::print_json(&mut stdout, forecasts[0], num_of_days, locale)?;
+    } else if format == "json" {
+        json::print_json_all(&mut stdout, &forecasts, num_of_days, locale)?;
+    } else if let Some(day) = compare {
+        match day {
+            Some(day) => render::print_day_comparison(&mut stdout, &forecasts, day, &settings)?,
+            None      => render::print_comparison(&mut stdout, &forecasts, &settings)?,
+        }
+    } else {
+        for (i, data) in forecasts.iter().enumerate() {
+            if let Some(ref template) = template {
+                writeln!(stdout, "{}", template.format(data, &settings))?;
+                continue;
+            }
+            if i > 0 {
+                writeln!(stdout)?;
+            }
+            if let Some(style) = chart_style {
+                chart::print_charts(&mut stdout, data, num_of_days, style, &settings)?;
+            } else if matches.opt_present("oneline") {
+                // the other views start with the location
+                if forecasts.len() > 1 {
+                    writeln!(stdout, "{}", data.location)?;
+                }
+                render::print_summary(&mut stdout, data, num_of_days, ChartStyle::detect(), &settings)?;
+            } else if matches.opt_present("hourly") {
+                render::print_hourly(&mut stdout, data, num_of_days, &settings)?;
+            } else {
+                render::print_forecast(&mut stdout, data, num_of_days, &settings)?;
+            }
         }
     }
 
-    fn format(&self) -> Vec<String> {
-        let icon = code_to_icon(self.weatherCode);
-        vec![
-            if unsafe { USE_ZH } {
-                format!("{} {:-15.15}", icon[0], self.lang_zh[0].value).fit_to_term_len(CELL_WIDTH)
-            } else {
-                format!("{} {:-15.15}", icon[0], self.weatherDesc[0].value).fit_to_term_len(CELL_WIDTH)
-            },
-            format!("{} {}", icon[1], self.format_temp()).fit_to_term_len(CELL_WIDTH),
-            format!("{} {}", icon[2], self.format_wind()).fit_to_term_len(CELL_WIDTH),
-            format!("{} {}", icon[3], self.format_visibility()).fit_to_term_len(CELL_WIDTH),
-            format!("{} {}", icon[4], self.format_rain()).fit_to_term_len(CELL_WIDTH)]
+    // report every failed location, the first one decides the exit code
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(first) => {
+            stdout.flush()?;
+            for e in errors {
+                eprintln!("{}: {}", args[0], e);
+            }
+            Err(first)
+        }
+        None        => Ok(()),
     }
 }
 
-
-fn print_usage(program: &str, opts: &Options) {
-    let brief = format!("Usage: {} [options] [CITY]", program);
-    print!("{}", opts.usage(&brief));
+/// `alias [list]`, `alias add NAME LOCATION` and `alias remove NAME`.
+fn run_alias(w: &mut dyn Write, args: &[String], mut aliases: Aliases, path: Option<PathBuf>) -> Result<()> {
+    let path = path.ok_or_else(|| Error::Config("no home directory to keep aliases in".to_string()))?;
+    match args.first().map(|a| &a[..]) {
+        None | Some("list") if args.len() <= 1 => {
+            let width = aliases.entries.keys().map(|name| name.len()).max().unwrap_or(0);
+            for (name, location) in &aliases.entries {
+                writeln!(w, "{:2$}  {}", name, location, width)?;
+            }
+            Ok(())
+        }
+        Some("add") if args.len() >= 3    => {
+            aliases.add(&args[1], &args[2..].join(" "))?;
+            aliases.save(&path)
+        }
+        Some("remove") if args.len() == 2 => {
+            aliases.remove(&args[1])?;
+            aliases.save(&path)
+        }
+        _                                 => Err(Error::Usage("usage: alias [list | add NAME LOCATION | remove NAME]"
+                                                              .to_string())),
+    }
 }
 
-fn main() {
-    let mut stdout = term::stdout().unwrap();
-
-    let args = env::args().collect::<Vec<String>>();
-
-    let mut opts = Options::new();
-
-    opts.optflag("h", "help", "print help message")
-        .optflag("",  "zh",   "use zh-cn locale")
-        .optopt("d",  "days", "number of days in output", "DAYS");
-
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(f) => panic!(f.to_string())
-    };
-
-    if matches.opt_present("h") {
-        print_usage(&args[0], &opts);
-        return;
+/// `search NAME`, the ten best matches of the gazetteer.
+fn run_search(w: &mut dyn Write, args: &[String], path: Option<PathBuf>, locale: Locale) -> Result<()> {
+    if args.is_empty() {
+        return Err(Error::Usage("usage: search NAME".to_string()));
     }
-
-    if matches.opt_present("zh") {
-        unsafe { USE_ZH = true; }
+    let path = path.ok_or_else(|| Error::Config("no home directory to find the gazetteer in".to_string()))?;
+    let query = args.join(" ");
+    let places = Gazetteer::load(&path)?.search(&query).into_iter().take(10)
+        .map(|place| (place.display_name(locale), location::format_coordinates(place.latitude, place.longitude),
+                      place.population))
+        .collect::<Vec<_>>();
+    if places.is_empty() {
+        return Err(Error::LocationNotFound(query));
     }
 
-    let num_of_days: usize = matches.opt_str("days").map(|ref s| usize::from_str(s).ok().expect("days must be a number")).unwrap_or(3);
-
-    let city = if !matches.free.is_empty() {
-        matches.free.join(" ")
-    } else {
-        "Beijing".to_string()
-    };
-
-    let mut url = Url::parse(BASE_URL).unwrap();
-    url.query_pairs_mut()
-        .clear()
-        .append_pair("q", &city)
-        .append_pair("key", KEY)
-        .append_pair("num_of_days", &num_of_days.to_string())
-        .append_pair("lang", "zh")
-        .append_pair("format", "json");
-
-    let client = Client::new();
-
-    let mut res = client.get(url).send().unwrap();
-
-    let mut buf = String::with_capacity(65535);
-    match res.read_to_string(&mut buf) {
-        Ok(_)  => (),
-        Err(e) => println!("err => {:?}", e)
+    // Chinese and Japanese names are twice as wide as they are long
+    let name_width = places.iter().map(|p| p.0.len_on_term()).max().unwrap_or(0);
+    let coordinates_width = places.iter().map(|p| p.1.chars().count()).max().unwrap_or(0);
+    for (name, coordinates, population) in places {
+        writeln!(w, "{}  {:3$}  {:>10}", name.fit_to_term_len(name_width), coordinates, population,
+                 coordinates_width)?;
     }
+    Ok(())
+}
 
-    let data = match json::decode::<DataWrapper>(buf.as_ref()) {
-        Ok(decoded) => decoded.data,
-        Err(_)      => unreachable!("Unable to decode {:?}", buf)
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("Weather for: {}\n\n", data.request[0].query);
+    fn is_usage_error(args: &[&str]) -> bool {
+        let args = ["weather", "-c", "/dev/null"].iter().chain(args).map(|a| a.to_string()).collect::<Vec<_>>();
+        matches!(run(&args), Err(Error::Usage(_)))
+    }
 
-    for line in data.current_condition[0].format() {
-        println!("{}", line);
+    #[test]
+    fn days() {
+        assert!(is_usage_error(&["--days", "0"]));
+        assert!(is_usage_error(&["--days", "-1"]));
+        assert!(is_usage_error(&["--days", "three"]));
     }
 
-    for w in data.weather.iter().take(num_of_days) {
-        w.print_day(&mut stdout).unwrap();
+    #[test]
+    fn one_view() {
+        let views = ["--hourly", "--chart", "--oneline", "--compare"];
+        for (i, view) in views.iter().enumerate() {
+            assert!(is_usage_error(&["--format", "json", view]), "--format json {}", view);
+            assert!(is_usage_error(&["--format", "%t", view]), "--format %t {}", view);
+            for other in &views[i + 1..] {
+                assert!(is_usage_error(&[view, other]), "{} {}", view, other);
+            }
+        }
     }
 }
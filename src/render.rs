@@ -0,0 +1,1176 @@
+// Terminal rendering of a forecast: the current condition and one table
+// per day, with icons and colours.
+
+use std::env;
+use std::io::prelude::*;
+use std::iter;
+use std::rc::Rc;
+use time::{self, strptime};
+
+use astro;
+use chart::{self, ChartStyle};
+use color::{self, ColorDepth};
+use error::{Error, Result};
+use forecast::{Condition, Forecast, Weather, WeatherCondition};
+use locale::{Locale, Text};
+use location;
+use theme::Theme;
+use units::UnitSystem;
+
+/// Width of one cell of the day table, in columns.
+pub static CELL_WIDTH: usize = 30;
+
+/// Width of a day table with four cells in a row.
+pub static WIDE: usize = 4 * (CELL_WIDTH + 1) + 1;
+
+/// Width of the rows of the hourly view, without the description.
+static HOURLY_WIDTH: usize = 60;
+
+/// How to render a forecast.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub units: UnitSystem,
+    pub locale: Locale,
+    /// Whether to emit colour escapes, see `ColorMode::enabled`.
+    pub color: bool,
+    pub depth: ColorDepth,
+    pub theme: Rc<Theme>,
+    /// Columns available, the day tables are laid out to fit.
+    pub width: usize,
+    /// Hours shown in the day tables, one cell each.
+    pub slots: Vec<u32>,
+    /// Adds humidity, dew point, cloud cover, UV index and pressure lines.
+    pub details: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            units: UnitSystem::Metric,
+            locale: Locale::En,
+            color: true,
+            depth: ColorDepth::Ansi256,
+            theme: Rc::new(Theme::default()),
+            width: WIDE,
+            slots: DEFAULT_SLOTS.to_vec(),
+            details: false,
+        }
+    }
+}
+
+impl Settings {
+    fn paint(&self, line: String) -> String {
+        if self.color { color::convert(&line, self.depth) } else { color::strip(&line) }
+    }
+
+    /// Table lines in the theme's frame colour.
+    pub fn frame(&self, s: &str) -> String {
+        match self.theme.frame {
+            Some(c) if self.color => c.paint(s, self.depth),
+            _                     => s.to_string(),
+        }
+    }
+
+    fn sun(&self) -> String {
+        color::recolor("\u{1b}[38;5;226m☀\u{1b}[0m", self.depth, &|i| self.theme.icon_color(i))
+    }
+
+    fn mini_icon(&self, cond: Condition) -> String {
+        color::recolor(condition_to_mini_icon(cond), self.depth, &|i| self.theme.icon_color(i))
+    }
+
+    /// An icon in the theme's colours.
+    fn icon(&self, cond: Condition) -> Vec<String> {
+        condition_to_icon(cond).iter()
+            .map(|line| color::recolor(line, self.depth, &|i| self.theme.icon_color(i)))
+            .collect()
+    }
+}
+
+/// Columns of the terminal on stdout, or `COLUMNS` when it isn't one or
+/// the platform can't tell. `None` if neither is known, callers then use
+/// `WIDE`.
+pub fn terminal_width() -> Option<usize> {
+    terminal_columns().or_else(|| env::var("COLUMNS").ok().and_then(|c| c.parse().ok()))
+}
+
+// The crate's only unsafe code, asking the terminal for its size.
+#[cfg(unix)]
+fn terminal_columns() -> Option<usize> {
+    let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    // TIOCGWINSZ only writes a winsize to the pointer, which outlives the call
+    match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_col > 0 => Some(size.ws_col as usize),
+        _                    => None,
+    }
+}
+
+// Without libc, e.g. on Windows, only `COLUMNS` tells.
+#[cfg(not(unix))]
+fn terminal_columns() -> Option<usize> {
+    None
+}
+
+/// Prints the location, the current condition and the first `days` days.
+pub fn print_forecast(w: &mut dyn Write, forecast: &Forecast, days: usize, settings: &Settings) -> Result<()> {
+    print_current(w, forecast, settings)?;
+    for (i, day) in forecast.days.iter().take(days).enumerate() {
+        day.print_day(w, astro::day_length_change(forecast, i), settings)?;
+    }
+    Ok(())
+}
+
+/// Like `print_forecast`, with every hourly entry of each day on a row of
+/// its own.
+pub fn print_hourly(w: &mut dyn Write, forecast: &Forecast, days: usize, settings: &Settings) -> Result<()> {
+    print_current(w, forecast, settings)?;
+    for day in forecast.days.iter().take(days) {
+        day.print_hours(w, settings)?;
+    }
+    Ok(())
+}
+
+/// One line per day, for prompts and status bars. The sparklines share a
+/// scale, so the days can be compared.
+pub fn print_summary(w: &mut dyn Write, forecast: &Forecast, days: usize, style: ChartStyle,
+                     settings: &Settings) -> Result<()> {
+    let days: Vec<&Weather> = forecast.days.iter().take(days).collect();
+    let temps = days.iter().flat_map(|d| d.hourly.iter()).map(|h| h.temp_c);
+    let range = (temps.clone().min().unwrap_or(0), temps.max().unwrap_or(0));
+    for day in days {
+        writeln!(w, "{}", day.format_summary(range, style, settings)?)?;
+    }
+    Ok(())
+}
+
+/// The current conditions of several locations side by side, one cell each.
+pub fn print_comparison(w: &mut dyn Write, forecasts: &[&Forecast], settings: &Settings) -> Result<()> {
+    let cells = forecasts.iter().map(|f| {
+        let mut cell = f.current.format(settings);
+        if settings.details {
+            cell.extend(f.current.format_details(f.days.first().and_then(|d| d.uv_index), settings));
+        }
+        cell
+    }).collect();
+    let labels = forecasts.iter().map(|f| f.location.clone()).collect();
+    let n = print_cells(w, settings, None, cells, labels)?;
+    writeln!(w, "{}", settings.frame(&rule("└", "┴", "┘", n)))?;
+    Ok(())
+}
+
+/// The `day`th day of several locations side by side, 0 being today.
+pub fn print_day_comparison(w: &mut dyn Write, forecasts: &[&Forecast], day: usize, settings: &Settings)
+                            -> Result<()> {
+    let days = forecasts.iter().map(|f| f.days.get(day)).collect::<Vec<_>>();
+    let date = match days.iter().flatten().next() {
+        Some(d) => center_on_term(&d.format_date(settings.locale)?, 11),
+        None    => return Ok(()),
+    };
+    let cells = days.iter().map(|d| match *d {
+        Some(d) => d.format_overview(settings),
+        None    => vec![" ".repeat(CELL_WIDTH); 5],
+    }).collect();
+    let labels = forecasts.iter().map(|f| f.location.clone()).collect();
+    let n = print_cells(w, settings, Some(&date), cells, labels)?;
+    writeln!(w, "{}", settings.frame(&rule("└", "┴", "┘", n)))?;
+    Ok(())
+}
+
+/// Prints the location and the current condition.
+pub fn print_current(w: &mut dyn Write, forecast: &Forecast, settings: &Settings) -> Result<()> {
+    match forecast.coordinates {
+        Some((lat, lon)) => writeln!(w, "{} {} ({})\n\n", settings.locale.text(Text::WeatherFor), forecast.location,
+                                     location::format_coordinates(lat, lon))?,
+        None             => writeln!(w, "{} {}\n\n", settings.locale.text(Text::WeatherFor), forecast.location)?,
+    }
+    for line in forecast.current.format(settings) {
+        writeln!(w, "{}", line)?;
+    }
+    if settings.details {
+        let uv = forecast.days.first().and_then(|d| d.uv_index);
+        for line in forecast.current.format_details(uv, settings) {
+            writeln!(w, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+pub trait HasTerminalDisplayLength {
+    fn len_on_term(&self) -> usize;
+    fn fit_to_term_len(&self, new_len: usize) -> String;
+}
+
+fn char_width_on_term(c: char) -> usize {
+    match c {
+        // http://blog.oasisfeng.com/2006/10/19/full-cjk-unicode-range/
+        '\u{3400}'..='\u{4DB5}' | '\u{4E00}'..='\u{9FA5}' | '\u{9FA6}'..='\u{9FBB}' |
+        '\u{F900}'..='\u{FA2D}' | '\u{FA30}'..='\u{FA6A}' | '\u{FA70}'..='\u{FAD9}' |
+        '\u{20000}'..='\u{2A6D6}' | '\u{2F800}'..='\u{2FA1D}' |
+        '\u{FF00}'..='\u{FFEF}' | '\u{2E80}'..='\u{2EFF}' |
+        '\u{3000}'..='\u{303F}' | '\u{31C0}'..='\u{31EF}' |
+        // Hiragana, Katakana
+        '\u{3040}'..='\u{30FF}' |
+        // emoji, e.g. the moon phases
+        '\u{1F300}'..='\u{1F64F}' | '\u{1F900}'..='\u{1F9FF}' =>
+            2,
+        _ =>
+            1
+    }
+}
+
+impl HasTerminalDisplayLength for String {
+    fn len_on_term(&self) -> usize {
+        let mut ret = 0usize;
+        let mut wait_for_color_mark_ends = false;
+
+        for c in self.chars() {
+            if c == '\u{1b}' && !wait_for_color_mark_ends {
+                wait_for_color_mark_ends = true;
+            } else if c == 'm' && wait_for_color_mark_ends {
+                wait_for_color_mark_ends = false;
+            } else if !wait_for_color_mark_ends {
+                ret += char_width_on_term(c);
+            }
+        }
+        ret
+    }
+
+    fn fit_to_term_len(&self, new_len: usize) -> String {
+        // cut by columns, never inside a wide char, keeping colour marks
+        let mut ret = String::with_capacity(self.len() + new_len);
+        let mut len = 0usize;
+        let mut wait_for_color_mark_ends = false;
+
+        for c in self.chars() {
+            if c == '\u{1b}' && !wait_for_color_mark_ends {
+                wait_for_color_mark_ends = true;
+                ret.push(c);
+            } else if wait_for_color_mark_ends {
+                wait_for_color_mark_ends = c != 'm';
+                ret.push(c);
+            } else if len + char_width_on_term(c) <= new_len {
+                len += char_width_on_term(c);
+                ret.push(c);
+            }
+        }
+        ret.extend(iter::repeat_n(' ', new_len - len));
+        ret
+    }
+}
+
+pub fn center_on_term(s: &str, width: usize) -> String {
+    let s = s.to_string();
+    let left = width.saturating_sub(s.len_on_term()) / 2;
+    format!("{:1$}{2}", "", left, s).fit_to_term_len(width)
+}
+
+pub fn wind_dir_to_icon(code: &str) -> &'static str {
+    match code {
+        "N"   => "\u{1b}[1m↓\u{1b}[0m",
+	      "NNE" => "\u{1b}[1m↓\u{1b}[0m",
+	      "NE"  => "\u{1b}[1m↙\u{1b}[0m",
+	      "ENE" => "\u{1b}[1m↙\u{1b}[0m",
+	      "E"   => "\u{1b}[1m←\u{1b}[0m",
+	      "ESE" => "\u{1b}[1m←\u{1b}[0m",
+	      "SE"  => "\u{1b}[1m↖\u{1b}[0m",
+	      "SSE" => "\u{1b}[1m↖\u{1b}[0m",
+	      "S"   => "\u{1b}[1m↑\u{1b}[0m",
+	      "SSW" => "\u{1b}[1m↑\u{1b}[0m",
+	      "SW"  => "\u{1b}[1m↗\u{1b}[0m",
+	      "WSW" => "\u{1b}[1m↗\u{1b}[0m",
+	      "W"   => "\u{1b}[1m→\u{1b}[0m",
+	      "WNW" => "\u{1b}[1m→\u{1b}[0m",
+	      "NW"  => "\u{1b}[1m↘\u{1b}[0m",
+	      "NNW" => "\u{1b}[1m↘\u{1b}[0m",
+        _     => " "
+    }
+}
+
+/// The five lines of the icon for a condition.
+pub fn condition_to_icon(cond: Condition) -> [&'static str; 5] {
+    match cond {
+        Condition::Unknown             => iconUnknown,
+        Condition::Sunny               => iconSunny,
+        Condition::PartlyCloudy        => iconPartlyCloudy,
+        Condition::Cloudy              => iconCloudy,
+        Condition::VeryCloudy          => iconVeryCloudy,
+        Condition::Fog                 => iconFog,
+        Condition::LightShowers        => iconLightShowers,
+        Condition::HeavyShowers        => iconHeavyShowers,
+        Condition::LightSnowShowers    => iconLightSnowShowers,
+        Condition::HeavySnowShowers    => iconHeavySnowShowers,
+        Condition::LightSleetShowers   => iconLightSleetShowers,
+        Condition::ThunderyShowers     => iconThunderyShowers,
+        Condition::ThunderyHeavyRain   => iconThunderyHeavyRain,
+        Condition::ThunderySnowShowers => iconThunderySnowShowers,
+        Condition::LightRain           => iconLightRain,
+        Condition::HeavyRain           => iconHeavyRain,
+        Condition::LightSnow           => iconLightSnow,
+        Condition::HeavySnow           => iconHeavySnow,
+        Condition::LightSleet          => iconLightSleet,
+    }
+}
+
+/// A three column icon for the hourly rows, in the colours of the big ones.
+pub fn condition_to_mini_icon(cond: Condition) -> &'static str {
+    match cond {
+        Condition::Unknown             => " ? ",
+        Condition::Sunny               => "\u{1b}[38;5;226m ☀ \u{1b}[0m",
+        Condition::PartlyCloudy        => "\u{1b}[38;5;226m☀\u{1b}[38;5;250m☁ \u{1b}[0m",
+        Condition::Cloudy              => "\u{1b}[38;5;250m ☁ \u{1b}[0m",
+        Condition::VeryCloudy          => "\u{1b}[38;5;240;1m☁☁ \u{1b}[0m",
+        Condition::Fog                 => "\u{1b}[38;5;251m ≡ \u{1b}[0m",
+        Condition::LightShowers        => "\u{1b}[38;5;226m☀\u{1b}[38;5;111m‘‘\u{1b}[0m",
+        Condition::HeavyShowers        => "\u{1b}[38;5;226m☀\u{1b}[38;5;21;1m‚‘\u{1b}[0m",
+        Condition::LightSnowShowers    => "\u{1b}[38;5;226m☀\u{1b}[38;5;255m* \u{1b}[0m",
+        Condition::HeavySnowShowers    => "\u{1b}[38;5;226m☀\u{1b}[38;5;255;1m**\u{1b}[0m",
+        Condition::LightSleetShowers   => "\u{1b}[38;5;226m☀\u{1b}[38;5;111m‘\u{1b}[38;5;255m*\u{1b}[0m",
+        Condition::ThunderyShowers     => "\u{1b}[38;5;226m☀\u{1b}[38;5;228;5m⚡\u{1b}[38;5;111;25m‘\u{1b}[0m",
+        Condition::ThunderyHeavyRain   => "\u{1b}[38;5;240;1m☁\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m‚\u{1b}[0m",
+        Condition::ThunderySnowShowers => "\u{1b}[38;5;226m☀\u{1b}[38;5;228;5m⚡\u{1b}[38;5;255;25m*\u{1b}[0m",
+        Condition::LightRain           => "\u{1b}[38;5;250m☁\u{1b}[38;5;111m‘‘\u{1b}[0m",
+        Condition::HeavyRain           => "\u{1b}[38;5;240;1m☁\u{1b}[38;5;21m‚‘\u{1b}[0m",
+        Condition::LightSnow           => "\u{1b}[38;5;250m☁\u{1b}[38;5;255m* \u{1b}[0m",
+        Condition::HeavySnow           => "\u{1b}[38;5;240;1m☁\u{1b}[38;5;255m**\u{1b}[0m",
+        Condition::LightSleet          => "\u{1b}[38;5;250m☁\u{1b}[38;5;111m‘\u{1b}[38;5;255m*\u{1b}[0m",
+    }
+}
+
+static iconUnknown: [&'static str; 5] = [
+		"    .-.      ",
+		"     __)     ",
+		"    (        ",
+		"     `-’     ",
+		"      •      "];
+static iconSunny: [&'static str; 5] = [
+		"\u{1b}[38;5;226m    \\   /    \u{1b}[0m",
+		"\u{1b}[38;5;226m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;226m  ― (   ) ―  \u{1b}[0m",
+		"\u{1b}[38;5;226m     `-’     \u{1b}[0m",
+		"\u{1b}[38;5;226m    /   \\    \u{1b}[0m"];
+static iconPartlyCloudy: [&'static str; 5] = [
+		"\u{1b}[38;5;226m   \\  /\u{1b}[0m      ",
+		"\u{1b}[38;5;226m _ /\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m   \\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
+		"             "];
+static iconCloudy: [&'static str; 5] = [
+		"             ",
+		"\u{1b}[38;5;250m     .--.    \u{1b}[0m",
+		"\u{1b}[38;5;250m  .-(    ).  \u{1b}[0m",
+		"\u{1b}[38;5;250m (___.__)__) \u{1b}[0m",
+		"             "];
+static iconVeryCloudy: [&'static str; 5] = [
+		"             ",
+		"\u{1b}[38;5;240;1m     .--.    \u{1b}[0m",
+		"\u{1b}[38;5;240;1m  .-(    ).  \u{1b}[0m",
+		"\u{1b}[38;5;240;1m (___.__)__) \u{1b}[0m",
+		"             "];
+static iconLightShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;111m     ‘ ‘ ‘ ‘ \u{1b}[0m",
+		"\u{1b}[38;5;111m    ‘ ‘ ‘ ‘  \u{1b}[0m"];
+static iconHeavyShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;240;1m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;240;1m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;240;1m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;21;1m   ‚‘‚‘‚‘‚‘  \u{1b}[0m",
+		"\u{1b}[38;5;21;1m   ‚’‚’‚’‚’  \u{1b}[0m"];
+static iconLightSnowShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;255m     *  *  * \u{1b}[0m",
+		"\u{1b}[38;5;255m    *  *  *  \u{1b}[0m"];
+static iconHeavySnowShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;240;1m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;240;1m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;240;1m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;255;1m    * * * *  \u{1b}[0m",
+		"\u{1b}[38;5;255;1m   * * * *   \u{1b}[0m"];
+static iconLightSleetShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;111m     ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m* \u{1b}[0m",
+		"\u{1b}[38;5;255m    *\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘  \u{1b}[0m"];
+static iconThunderyShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;228;5m    ⚡\u{1b}[38;5;111;25m‘ ‘\u{1b}[38;5;228;5m⚡\u{1b}[38;5;111;25m‘ ‘ \u{1b}[0m",
+		"\u{1b}[38;5;111m    ‘ ‘ ‘ ‘  \u{1b}[0m"];
+static iconThunderyHeavyRain: [&'static str; 5] = [
+		"\u{1b}[38;5;240;1m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;240;1m    (   ).   \u{1b}[0m",
+		"\u{1b}[38;5;240;1m   (___(__)  \u{1b}[0m",
+		"\u{1b}[38;5;21;1m  ‚‘\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m‘‚\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m‚‘   \u{1b}[0m",
+		"\u{1b}[38;5;21;1m  ‚’‚’\u{1b}[38;5;228;5m⚡\u{1b}[38;5;21;25m’‚’   \u{1b}[0m"];
+static iconThunderySnowShowers: [&'static str; 5] = [
+		"\u{1b}[38;5;226m _`/\"\"\u{1b}[38;5;250m.-.    \u{1b}[0m",
+		"\u{1b}[38;5;226m  ,\\_\u{1b}[38;5;250m(   ).  \u{1b}[0m",
+		"\u{1b}[38;5;226m   /\u{1b}[38;5;250m(___(__) \u{1b}[0m",
+		"\u{1b}[38;5;255m     *\u{1b}[38;5;228;5m⚡\u{1b}[38;5;255;25m *\u{1b}[38;5;228;5m⚡\u{1b}[38;5;255;25m * \u{1b}[0m",
+		"\u{1b}[38;5;255m    *  *  *  \u{1b}[0m"];
+static iconLightRain: [&'static str; 5] = [
+		"\u{1b}[38;5;250m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;250m    (   ).   \u{1b}[0m",
+		"\u{1b}[38;5;250m   (___(__)  \u{1b}[0m",
+		"\u{1b}[38;5;111m    ‘ ‘ ‘ ‘  \u{1b}[0m",
+		"\u{1b}[38;5;111m   ‘ ‘ ‘ ‘   \u{1b}[0m"];
+static iconHeavyRain: [&'static str; 5] = [
+		"\u{1b}[38;5;240;1m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;240;1m    (   ).   \u{1b}[0m",
+		"\u{1b}[38;5;240;1m   (___(__)  \u{1b}[0m",
+		"\u{1b}[38;5;21;1m  ‚‘‚‘‚‘‚‘   \u{1b}[0m",
+		"\u{1b}[38;5;21;1m  ‚’‚’‚’‚’   \u{1b}[0m"];
+static iconLightSnow: [&'static str; 5] = [
+		"\u{1b}[38;5;250m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;250m    (   ).   \u{1b}[0m",
+		"\u{1b}[38;5;250m   (___(__)  \u{1b}[0m",
+		"\u{1b}[38;5;255m    *  *  *  \u{1b}[0m",
+		"\u{1b}[38;5;255m   *  *  *   \u{1b}[0m"];
+static iconHeavySnow: [&'static str; 5] = [
+		"\u{1b}[38;5;240;1m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;240;1m    (   ).   \u{1b}[0m",
+		"\u{1b}[38;5;240;1m   (___(__)  \u{1b}[0m",
+		"\u{1b}[38;5;255;1m   * * * *   \u{1b}[0m",
+		"\u{1b}[38;5;255;1m  * * * *    \u{1b}[0m"];
+static iconLightSleet: [&'static str; 5] = [
+		"\u{1b}[38;5;250m     .-.     \u{1b}[0m",
+		"\u{1b}[38;5;250m    (   ).   \u{1b}[0m",
+		"\u{1b}[38;5;250m   (___(__)  \u{1b}[0m",
+		"\u{1b}[38;5;111m    ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m*  \u{1b}[0m",
+		"\u{1b}[38;5;255m   *\u{1b}[38;5;111m ‘ \u{1b}[38;5;255m*\u{1b}[38;5;111m ‘   \u{1b}[0m"];
+static iconFog: [&'static str; 5] = [
+		"             ",
+		"\u{1b}[38;5;251m _ - _ - _ - \u{1b}[0m",
+		"\u{1b}[38;5;251m  _ - _ - _  \u{1b}[0m",
+		"\u{1b}[38;5;251m _ - _ - _ - \u{1b}[0m",
+		"             "];
+
+impl Weather {
+    /// The date as the day tables show it, e.g. "Sun 18. Oct".
+    pub fn format_date(&self, locale: Locale) -> Result<String> {
+        let date = strptime(&self.date, "%Y-%m-%d").map_err(|_| Error::Date(self.date.clone()))?;
+        Ok(locale.format_date(&time::at_utc(date.to_timespec())))
+    }
+
+    /// Prints the day table, as many slots per row as fit in the width, and
+    /// the astronomy panel below it, `change` being how many minutes the day
+    /// is longer than the one before.
+    pub fn print_day(&self, w: &mut dyn Write, change: Option<i32>, settings: &Settings) -> Result<()> {
+        let locale = settings.locale;
+        let date = center_on_term(&self.format_date(locale)?, 11);
+
+        let labels = settings.slots.iter().map(|&h| slot_label(h, locale)).collect();
+        let n = print_cells(w, settings, Some(&date), self.format_day(settings), labels)?;
+
+        let f = |s: &str| settings.frame(s);
+        let inner = n * (CELL_WIDTH + 1) - 1;
+        let panel = self.format_astronomy(change, inner - 2, settings);
+        if panel.is_empty() {
+            writeln!(w, "{}", f(&rule("└", "┴", "┘", n)))?;
+            return Ok(());
+        }
+        writeln!(w, "{}", f(&rule("├", "┴", "┤", n)))?;
+        for line in panel {
+            writeln!(w, "{0} {1} {0}", f("│"), line.fit_to_term_len(inner - 2))?;
+        }
+        writeln!(w, "{}", f(&rule("└", "─", "┘", n)))?;
+        Ok(())
+    }
+
+    /// Prints a row for each hourly entry under a line with the date.
+    pub fn print_hours(&self, w: &mut dyn Write, settings: &Settings) -> Result<()> {
+        let date = self.format_date(settings.locale)?;
+        let fill = HOURLY_WIDTH.saturating_sub(date.len_on_term() + 6);
+
+        writeln!(w)?;
+        writeln!(w, "{}{}{}", settings.frame("──┤ "), date, settings.frame(&format!(" ├{}", "─".repeat(fill))))?;
+        for h in self.hourly.iter() {
+            writeln!(w, "{}", h.format_row(settings))?;
+        }
+        Ok(())
+    }
+
+    /// Date, the icon at noon, lowest and highest temperature, a sparkline
+    /// of the hourly temperatures on the scale of `range` and the day's
+    /// precipitation.
+    pub fn format_summary(&self, range: (i32, i32), style: ChartStyle, settings: &Settings) -> Result<String> {
+        let condition = self.at_hour(12).map_or(Condition::Unknown, |h| h.condition);
+        let temps: Vec<f64> = self.hourly.iter().map(|h| h.temp_c as f64).collect();
+        let spark = chart::sparkline(&temps, (range.0 as f64, range.1 as f64), style).iter()
+            .zip(self.hourly.iter())
+            .map(|(glyph, h)| color::scale_color(&settings.theme.temperature, h.temp_c, settings.depth)
+                 .paint(glyph, settings.depth))
+            .collect::<String>();
+        let temp = format!("{} - {} {}", colorized_temp(self.min_temp_c, settings),
+                           colorized_temp(self.max_temp_c, settings), settings.units.temp_unit());
+        let precip: f32 = self.hourly.iter().map(|h| h.precip_mm).sum();
+
+        Ok(settings.paint(format!("{}  {}  {}  {}  {}",
+                                  self.format_date(settings.locale)?.fit_to_term_len(11),
+                                  settings.mini_icon(condition),
+                                  temp.fit_to_term_len(12),
+                                  spark,
+                                  settings.units.format_precip(precip))))
+    }
+
+    /// The cells of the day-part slots.
+    pub fn format_day(&self, settings: &Settings) -> Vec<Vec<String>> {
+        let lines = if settings.details { 8 } else { 5 };
+        settings.slots.iter().map(|&hour| match self.at_hour(hour) {
+            Some(h) if settings.details => {
+                let mut cell = h.format(settings);
+                cell.extend(h.format_details(self.uv_index, settings));
+                cell
+            }
+            Some(h)                     => h.format(settings),
+            None                        => vec![" ".repeat(CELL_WIDTH); lines],
+        }).collect()
+    }
+
+    /// A cell for the whole day: the dominant condition, the lowest and
+    /// highest temperature, the strongest wind, sunrise and sunset, and the
+    /// day's precipitation with the highest chance of rain.
+    pub fn format_overview(&self, settings: &Settings) -> Vec<String> {
+        let units = settings.units;
+        let dominant = self.dominant();
+        let icon = settings.icon(dominant.map_or(Condition::Unknown, |h| h.condition));
+        let desc = dominant.map_or("", |h| h.description(settings.locale));
+        let temp = format!("{} - {} {}", colorized_temp(self.min_temp_c, settings),
+                           colorized_temp(self.max_temp_c, settings), units.temp_unit());
+        let wind = self.hourly.iter()
+            .max_by_key(|h| h.wind_speed_kmph)
+            .map_or_else(String::new, |h| h.format_wind(settings));
+        let time = |t: &Option<String>| t.clone().unwrap_or_else(|| "-".to_string());
+        let sun = format!("{} {} - {}", settings.sun(), time(&self.astronomy.sunrise), time(&self.astronomy.sunset));
+        let precip = units.format_precip(self.hourly.iter().map(|h| h.precip_mm).sum());
+        let rain = match self.hourly.iter().filter_map(|h| h.chance_of_rain).max() {
+            Some(chance) => format!("{} | {}%", precip, chance),
+            None         => precip,
+        };
+        vec![
+            format!("{} {:-15.15}", icon[0], desc).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[1], temp).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[2], wind).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[3], sun).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[4], rain).fit_to_term_len(CELL_WIDTH)]
+            .into_iter()
+            .map(|line| settings.paint(line))
+            .collect()
+    }
+
+    /// Sun and moon times, day length and moon phase, wrapped to `width`.
+    pub fn format_astronomy(&self, change: Option<i32>, width: usize, settings: &Settings) -> Vec<String> {
+        let astronomy = &self.astronomy;
+        let time = |t: &Option<String>| t.clone().unwrap_or_else(|| "-".to_string());
+        let mut parts = Vec::new();
+        if astronomy.sunrise.is_some() || astronomy.sunset.is_some() {
+            parts.push(format!("{} {} - {}", settings.sun(), time(&astronomy.sunrise), time(&astronomy.sunset)));
+        }
+        if let Some(length) = astronomy.day_length() {
+            parts.push(match change {
+                Some(change) => format!("{}h{:02}m ({:+}m)", length / 60, length % 60, change),
+                None         => format!("{}h{:02}m", length / 60, length % 60),
+            });
+        }
+        if astronomy.moonrise.is_some() || astronomy.moonset.is_some() {
+            parts.push(format!("☾ {} - {}", time(&astronomy.moonrise), time(&astronomy.moonset)));
+        }
+        match (astronomy.moon_phase, astronomy.moon_illumination) {
+            (Some(phase), Some(lit)) => parts.push(format!("{} {}%", astro::moon_glyph(phase), lit)),
+            (None, Some(lit))        => parts.push(format!("☾ {}%", lit)),
+            _                        => {}
+        }
+
+        // as many parts on a line as fit
+        let mut lines: Vec<String> = Vec::new();
+        for part in parts {
+            match lines.last_mut() {
+                Some(line) if line.len_on_term() + 3 + part.len_on_term() <= width => {
+                    line.push_str("   ");
+                    line.push_str(&part);
+                }
+                _ => lines.push(part),
+            }
+        }
+        lines.into_iter().map(|line| settings.paint(line)).collect()
+    }
+}
+
+/// Morning, Noon, Evening and Night.
+pub static DEFAULT_SLOTS: [u32; 4] = [9, 12, 18, 21];
+
+/// Parses slot hours, e.g. "6,9,12,15,18,21". They're sorted, at least one
+/// and at most 24.
+pub fn parse_slots(s: &str) -> Option<Vec<u32>> {
+    let mut slots = Vec::new();
+    for hour in s.split(',') {
+        match hour.trim().parse() {
+            Ok(h) if h < 24 => slots.push(h),
+            _               => return None,
+        }
+    }
+    slots.sort();
+    slots.dedup();
+    Some(slots)
+}
+
+/// The default slots keep their names, others are labelled with the time.
+fn slot_label(hour: u32, locale: Locale) -> String {
+    match hour {
+        9  => locale.text(Text::Morning).to_string(),
+        12 => locale.text(Text::Noon).to_string(),
+        18 => locale.text(Text::Evening).to_string(),
+        21 => locale.text(Text::Night).to_string(),
+        h  => format!("{:02}:00", h),
+    }
+}
+
+/// Table cells per row: as many as fit in `width`, spread evenly over the
+/// rows. Four slots make one row of four, two of two or four of one.
+fn cells_per_row(cells: usize, width: usize) -> usize {
+    let cells = cells.max(1);
+    let fit = (width.saturating_sub(1) / (CELL_WIDTH + 1)).max(1);
+    let rows = cells.div_ceil(fit);
+    cells.div_ceil(rows)
+}
+
+/// Lays out `cells` in rows that fit the width, each under its label, the
+/// first row under the date box when there is a date. Returns the cells per
+/// row; the bottom line is left to the caller.
+fn print_cells(w: &mut dyn Write, settings: &Settings, date: Option<&str>, mut cells: Vec<Vec<String>>,
+               labels: Vec<String>) -> Result<usize> {
+    let mut labels = labels.iter()
+        .map(|l| l.fit_to_term_len(CELL_WIDTH).trim_end().to_string())
+        .collect::<Vec<_>>();
+    let lines = cells.first().map_or(0, |c| c.len());
+    let n = cells_per_row(cells.len(), settings.width);
+    // fill up the last row
+    while !cells.len().is_multiple_of(n) {
+        cells.push(vec![" ".repeat(CELL_WIDTH); lines]);
+        labels.push(String::new());
+    }
+    let labels = labels.iter().map(|l| &l[..]).collect::<Vec<_>>();
+
+    let f = |s: &str| settings.frame(s);
+    let sep = f("|");
+    for (row, (cells, labels)) in cells.chunks(n).zip(labels.chunks(n)).enumerate() {
+        match date {
+            Some(date) if row == 0 => print_header(w, settings, date, labels)?,
+            _                      => {
+                writeln!(w, "{}", f(&if row == 0 { rule("┌", "┬", "┐", n) } else { rule("├", "┼", "┤", n) }))?;
+                writeln!(w, "{0}{1}{0}", f("│"), labels.iter()
+                         .map(|l| center_on_term(l, CELL_WIDTH))
+                         .collect::<Vec<_>>()
+                         .join(&f("│")))?;
+            }
+        }
+        writeln!(w, "{}", f(&rule("├", "┼", "┤", n)))?;
+        for i in 0..lines {
+            writeln!(w, "{0}{1}{0}", sep, cells.iter()
+                     .map(|c| &c[i][..])
+                     .collect::<Vec<_>>()
+                     .join(&sep))?;
+        }
+    }
+    Ok(n)
+}
+
+/// A horizontal table line for `n` cells.
+fn rule(left: &str, middle: &str, right: &str, n: usize) -> String {
+    format!("{}{}{}", left, vec!["─".repeat(CELL_WIDTH); n].join(middle), right)
+}
+
+/// The top of a day table with the date box in the middle. With an even
+/// number of cells the box hangs over the two middle labels, otherwise it
+/// sits in the top line.
+fn print_header(w: &mut dyn Write, settings: &Settings, date: &str, labels: &[&str]) -> Result<()> {
+    let f = |s: &str| settings.frame(s);
+    let n = labels.len();
+    let width = n * (CELL_WIDTH + 1) + 1;
+    let left = (width - 15) / 2;
+
+    let top = rule("┌", "┬", "┐", n).chars().collect::<Vec<_>>();
+    let top_left = top[..left].iter().collect::<String>();
+    let top_right = top[left + 15..].iter().collect::<String>();
+
+    if n % 2 == 1 {
+        writeln!(w, "{}{}{}", f(&(top_left + "┤ ")), date, f(&(" ├".to_string() + &top_right)))?;
+        writeln!(w, "{0}{1}{0}", f("│"), labels.iter()
+                 .map(|l| center_on_term(l, CELL_WIDTH))
+                 .collect::<Vec<_>>()
+                 .join(&f("│")))?;
+        return Ok(());
+    }
+
+    // the date box covers the inner edges of the two middle cells
+    let (before, after) = labels.split_at(n / 2);
+    let (before, noon) = before.split_at(n / 2 - 1);
+    let (evening, after) = after.split_at(1);
+    let (noon, evening) = (noon[0], evening[0]);
+    let noon = format!("{:1$}{2}", "", (CELL_WIDTH - noon.to_string().len_on_term()) / 2, noon);
+    let evening = format!("{:1$}{2}", "", ((CELL_WIDTH - evening.to_string().len_on_term()) / 2).saturating_sub(7), evening);
+    let cells = |labels: &[&str]| labels.iter()
+        .map(|l| center_on_term(l, CELL_WIDTH) + &f("│"))
+        .collect::<String>();
+
+    writeln!(w, "{}", f(&format!("{:2$}┌─────────────┐{:2$}", "", "", left)))?;
+    writeln!(w, "{}{}{}", f(&(top_left + "┤ ")), date, f(&(" ├".to_string() + &top_right)))?;
+    writeln!(w, "{}{}{}{}{}{}{}",
+             f("│"), cells(before),
+             noon.fit_to_term_len(CELL_WIDTH - 7),
+             f("└──────┬──────┘"),
+             evening.fit_to_term_len(CELL_WIDTH - 7),
+             f("│"), cells(after))?;
+    Ok(())
+}
+
+
+// colours are picked from the metric value, so they mean the same in any unit
+fn colorized_temp(temp: i32, settings: &Settings) -> String {
+    let value = settings.units.temp(temp).to_string();
+    match settings.depth {
+        // underline frost, bold heat
+        ColorDepth::Mono if temp < 0   => format!("\u{1b}[4m{}\u{1b}[0m", value),
+        ColorDepth::Mono if temp >= 28 => format!("\u{1b}[1m{}\u{1b}[0m", value),
+        depth                          => color::scale_color(&settings.theme.temperature, temp, depth).paint(&value, depth),
+    }
+}
+
+fn colorized_wind(spd: i32, settings: &Settings) -> String {
+    let value = settings.units.speed(spd).to_string();
+    match settings.depth {
+        ColorDepth::Mono if spd >= 28 => format!("\u{1b}[1m{}\u{1b}[0m", value),
+        depth                         => color::scale_color(&settings.theme.wind, spd, depth).paint(&value, depth),
+    }
+}
+
+fn colorized_humidity(humidity: i32, settings: &Settings) -> String {
+    let value = format!("{}%", humidity);
+    match settings.depth {
+        ColorDepth::Mono => value,
+        depth            => color::scale_color(&settings.theme.humidity, humidity, depth).paint(&value, depth),
+    }
+}
+
+// bold from "very high" on
+fn colorized_uv(uv: i32, settings: &Settings) -> String {
+    let value = uv.to_string();
+    match settings.depth {
+        ColorDepth::Mono if uv >= 8 => format!("\u{1b}[1m{}\u{1b}[0m", value),
+        depth                       => color::scale_color(&settings.theme.uv, uv, depth).paint(&value, depth),
+    }
+}
+
+
+impl WeatherCondition {
+    fn format_visibility(&self, units: UnitSystem) -> String {
+        format!("{} {}", units.distance(self.visibility_km), units.distance_unit())
+    }
+
+    fn format_wind(&self, settings: &Settings) -> String {
+        let units = settings.units;
+        let wind_gust = self.wind_gust_kmph.unwrap_or(0);
+        if wind_gust > self.wind_speed_kmph {
+            format!("{} {} - {} {}      ",
+                    wind_dir_to_icon(self.wind_dir.as_ref()),
+                    colorized_wind(self.wind_speed_kmph, settings),
+                    colorized_wind(wind_gust, settings),
+                    units.speed_unit())
+        } else {
+            format!("{} {} {}      ",
+                    wind_dir_to_icon(self.wind_dir.as_ref()),
+                    colorized_wind(self.wind_speed_kmph, settings),
+                    units.speed_unit())
+        }
+    }
+
+    fn format_temp(&self, settings: &Settings) -> String {
+        let units = settings.units;
+        if self.feels_like_c < self.temp_c {
+            format!("{} - {} {}         ",
+                    colorized_temp(self.feels_like_c, settings),
+                    colorized_temp(self.temp_c, settings),
+                    units.temp_unit())
+        } else if self.feels_like_c > self.temp_c {
+            format!("{} - {} {}         ",
+                    colorized_temp(self.temp_c, settings),
+                    colorized_temp(self.feels_like_c, settings),
+                    units.temp_unit())
+        } else {
+            format!("{} {}             ",
+                    colorized_temp(self.feels_like_c, settings),
+                    units.temp_unit())
+        }
+    }
+
+    fn format_rain(&self, units: UnitSystem) -> String {
+        match self.chance_of_rain {
+            Some(ratio) =>
+                format!("{} | {}%        ", units.format_precip(self.precip_mm), ratio),
+            None =>
+                format!("{}              ", units.format_precip(self.precip_mm))
+        }
+    }
+
+    /// The provider's description when it speaks our language, our own
+    /// translation of the condition otherwise.
+    pub fn description(&self, locale: Locale) -> &str {
+        match self.local_desc {
+            _ if locale == Locale::En => &self.desc,
+            Some(ref local)           => local,
+            None                      => locale.condition(self.condition),
+        }
+    }
+
+    /// One row of the hourly view: time, icon, temperature, wind, rain and
+    /// description.
+    pub fn format_row(&self, settings: &Settings) -> String {
+        let time = match self.hour {
+            Some(h) => format!("{:02}:00", h),
+            None    => "     ".to_string(),
+        };
+        settings.paint(format!(" {}  {}  {}{}{}{}",
+                               time,
+                               settings.mini_icon(self.condition),
+                               self.format_temp(settings).fit_to_term_len(14),
+                               self.format_wind(settings).fit_to_term_len(17),
+                               self.format_rain(settings.units).fit_to_term_len(16),
+                               self.description(settings.locale)))
+    }
+
+    /// Two more lines for the cell: humidity and dew point, then cloud
+    /// cover, the day's UV index and pressure.
+    pub fn format_details(&self, uv: Option<i32>, settings: &Settings) -> Vec<String> {
+        let (locale, units) = (settings.locale, settings.units);
+        // three short lines rather than two long ones, "Влажность" and
+        // "Point de rosée" wouldn't fit in a cell next to each other
+        let humidity = format!(" {} {}  {} {}%", locale.text(Text::Humidity),
+                               colorized_humidity(self.humidity, settings), locale.text(Text::Clouds),
+                               self.cloud_cover);
+        let dewpoint = self.dewpoint_c.map_or(String::new(), |dewpoint| {
+            format!(" {} {} {}", locale.text(Text::DewPoint), colorized_temp(dewpoint, settings), units.temp_unit())
+        });
+        let sky = uv.map(|uv| format!("UV {}", colorized_uv(uv, settings))).into_iter()
+            .chain(self.pressure_hpa.map(|pressure| units.format_pressure(pressure)))
+            .collect::<Vec<_>>();
+        vec![humidity, dewpoint, format!(" {}", sky.join("  "))].into_iter()
+            .map(|line| settings.paint(line.fit_to_term_len(CELL_WIDTH)))
+            .collect()
+    }
+
+    pub fn format(&self, settings: &Settings) -> Vec<String> {
+        let units = settings.units;
+        let icon = settings.icon(self.condition);
+        let desc = self.description(settings.locale);
+        vec![
+            format!("{} {:-15.15}", icon[0], desc).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[1], self.format_temp(settings)).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[2], self.format_wind(settings)).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[3], self.format_visibility(units)).fit_to_term_len(CELL_WIDTH),
+            format!("{} {}", icon[4], self.format_rain(units)).fit_to_term_len(CELL_WIDTH)]
+            .into_iter()
+            .map(|line| settings.paint(line))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::sample_forecast;
+
+    fn settings(locale: Locale) -> Settings {
+        Settings { locale, ..Settings::default() }
+    }
+
+    fn render(locale: Locale) -> String {
+        let mut out = Vec::new();
+        print_forecast(&mut out, &sample_forecast(), 2, &settings(locale)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn terminal_widths() {
+        assert_eq!("天气 ok".to_string().len_on_term(), 7);
+        assert_eq!("\u{1b}[38;5;226m22\u{1b}[0m".to_string().len_on_term(), 2);
+        assert_eq!("天气预报".to_string().fit_to_term_len(5), "天气 ");
+        assert_eq!(center_on_term("朝", 5), " 朝  ");
+    }
+
+    #[test]
+    fn day_tables_line_up() {
+        for &locale in [Locale::En, Locale::ZhCn, Locale::Ja].iter() {
+            let out = render(locale);
+            assert!(out.starts_with(locale.text(Text::WeatherFor)));
+            let table = out.lines().filter(|l| l.starts_with('│') || l.starts_with('|'));
+            for line in table {
+                assert_eq!(line.to_string().len_on_term(), 4 * (CELL_WIDTH + 1) + 1, "{:?}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn layouts() {
+        assert_eq!(cells_per_row(4, 200), 4);
+        assert_eq!(cells_per_row(4, WIDE), 4);
+        assert_eq!(cells_per_row(4, WIDE - 1), 2);
+        assert_eq!(cells_per_row(4, 63), 2);
+        assert_eq!(cells_per_row(4, 62), 1);
+        assert_eq!(cells_per_row(4, 20), 1);
+        assert_eq!(cells_per_row(6, WIDE), 3);
+
+        let forecast = sample_forecast();
+        for &(width, table_width) in [(100, 63), (40, 32)].iter() {
+            let mut out = Vec::new();
+            print_forecast(&mut out, &forecast, 2, &Settings { width, ..Settings::default() }).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            let tables = out.lines().skip_while(|l| !l.starts_with('┌'));
+            for line in tables.filter(|l| !l.trim().is_empty()) {
+                assert_eq!(line.to_string().len_on_term(), table_width, "{:?}", line);
+            }
+            assert!(out.contains(Locale::En.text(Text::Night)));
+        }
+    }
+
+    #[test]
+    fn astronomy() {
+        let mut forecast = sample_forecast();
+        let settings = Settings { color: false, ..Settings::default() };
+        let day = &forecast.days[0];
+        assert_eq!(day.format_astronomy(Some(-2), 100, &settings),
+                   vec!["☀ 06:31 - 17:31   11h00m (-2m)   🌓 47%"]);
+        assert_eq!(day.format_astronomy(None, 20, &settings),
+                   vec!["☀ 06:31 - 17:31", "11h00m   🌓 47%"]);
+        assert_eq!(astro::day_length_change(&forecast, 0), Some(-2));
+        assert_eq!(astro::day_length_change(&forecast, 1), Some(-3));
+
+        // computed when the provider has no times, nothing without a place
+        forecast.days[0].astronomy = Default::default();
+        forecast.days[1].astronomy = Default::default();
+        astro::complete(&mut forecast);
+        assert_eq!(forecast.days[0].astronomy.sunrise, Some("06:27".to_string()));
+        forecast.coordinates = None;
+        forecast.days[0].astronomy = Default::default();
+        astro::complete(&mut forecast);
+        assert_eq!(forecast.days[0].format_astronomy(None, 100, &settings), vec!["🌓 47%"]);
+
+        // the panel lines up with the table in every layout
+        for &width in [WIDE, 63, 20].iter() {
+            let mut out = Vec::new();
+            print_forecast(&mut out, &sample_forecast(), 2, &Settings { width, ..settings.clone() }).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(out.contains("🌓 47%"));
+            let table = out.lines().skip_while(|l| !l.starts_with('┌'));
+            let widths = table.filter(|l| !l.trim().is_empty()).map(|l| l.to_string().len_on_term());
+            assert_eq!(widths.collect::<::std::collections::BTreeSet<_>>().len(), 1);
+        }
+    }
+
+    #[test]
+    fn comparison() {
+        let beijing = sample_forecast();
+        let mut berlin = sample_forecast();
+        berlin.location = "Berlin, a name much too long for a cell".to_string();
+        berlin.current.temp_c = -3;
+        let forecasts = [&beijing, &berlin, &beijing];
+
+        for &(width, table_width) in [(WIDE, 94), (63, 63), (20, 32)].iter() {
+            let mut out = Vec::new();
+            print_comparison(&mut out, &forecasts, &Settings { width, color: false, ..Settings::default() }).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            for line in out.lines() {
+                assert_eq!(line.to_string().len_on_term(), table_width, "{:?}", line);
+            }
+            assert_eq!(out.matches("Beijing, China").count(), 2);
+            assert!(out.contains("Berlin, a name much too long "));
+            assert!(out.contains("-3 - 12 °C"), "{}", out);
+        }
+
+        // the partly cloudy morning outlasts the afternoon's clouds and rain
+        let dominant = beijing.days[0].dominant().unwrap();
+        assert_eq!((dominant.condition, dominant.hour), (Condition::PartlyCloudy, Some(6)));
+
+        // tomorrow, Berlin has only today
+        berlin.days.truncate(1);
+        let forecasts = [&beijing, &berlin];
+        let settings = Settings { color: false, ..Settings::default() };
+        let mut out = Vec::new();
+        print_day_comparison(&mut out, &forecasts, 1, &settings).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for line in out.lines() {
+            assert_eq!(line.to_string().len_on_term(), 63, "{:?}", line);
+        }
+        assert!(out.contains("Mon 19. Oct"));
+        assert!(out.contains("Overcast        |                              |"));
+        assert!(out.contains("4 - 16 °C"));
+        assert!(out.contains("☀ 06:32 - 17:29"));
+        assert!(out.contains("3.9 mm | 60%"));
+
+        let mut out = Vec::new();
+        print_day_comparison(&mut out, &forecasts, 2, &settings).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn slots() {
+        assert_eq!(parse_slots("21, 6,12,6"), Some(vec![6, 12, 21]));
+        assert_eq!(parse_slots("6,24"), None);
+        assert_eq!(parse_slots(""), None);
+
+        // 3-hourly data, the 6 and 9 o'clock slots still get their own hours
+        let mut forecast = sample_forecast();
+        for day in forecast.days.iter_mut() {
+            day.hourly.retain(|h| h.hour.unwrap() % 3 == 1);
+        }
+        assert_eq!(forecast.days[0].at_hour(6).and_then(|h| h.hour), Some(7));
+        assert_eq!(forecast.days[0].at_hour(9).and_then(|h| h.hour), Some(10));
+
+        let settings = Settings { slots: vec![6, 9, 12, 15, 18, 21], color: false, ..Settings::default() };
+        assert_eq!(forecast.days[0].format_day(&settings).len(), 6);
+        let mut out = Vec::new();
+        print_forecast(&mut out, &forecast, 1, &settings).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let labels = |a, b, c| format!("│{}│{}│{}│", center_on_term(a, CELL_WIDTH),
+                                        center_on_term(b, CELL_WIDTH), center_on_term(c, CELL_WIDTH));
+        assert!(out.contains(&labels("06:00", "Morning", "Noon")));
+        assert!(out.contains(&labels("15:00", "Evening", "Night")));
+    }
+
+    #[test]
+    fn no_color() {
+        let forecast = sample_forecast();
+        let mut colored = Vec::new();
+        print_forecast(&mut colored, &forecast, 2, &settings(Locale::En)).unwrap();
+        let mut plain = Vec::new();
+        print_forecast(&mut plain, &forecast, 2, &Settings { color: false, ..settings(Locale::En) }).unwrap();
+
+        let plain = String::from_utf8(plain).unwrap();
+        assert!(!plain.contains('\u{1b}'));
+        assert_eq!(plain, color::strip(&String::from_utf8(colored).unwrap()));
+    }
+
+    #[test]
+    fn color_depths() {
+        let forecast = sample_forecast();
+        let render = |depth| {
+            let mut out = Vec::new();
+            print_forecast(&mut out, &forecast, 2, &Settings { depth, ..Settings::default() }).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let ansi256 = render(ColorDepth::Ansi256);
+        assert!(ansi256.contains("\u{1b}[38;5;226m"));
+
+        let truecolor = render(ColorDepth::TrueColor);
+        assert!(truecolor.contains("\u{1b}[38;2;255;255;0m"));
+        assert!(!truecolor.contains("38;5;"));
+
+        let ansi16 = render(ColorDepth::Ansi16);
+        assert!(ansi16.contains("\u{1b}[93m"));
+        assert!(!ansi16.contains("38;"));
+
+        let mono = render(ColorDepth::Mono);
+        assert!(!mono.contains("38;"));
+        assert!(mono.contains("\u{1b}[1m"));
+
+        for out in [truecolor, ansi16, mono].iter() {
+            assert_eq!(color::strip(out), color::strip(&ansi256));
+        }
+    }
+
+    #[test]
+    fn themes() {
+        let forecast = sample_forecast();
+        let mut default = Vec::new();
+        print_forecast(&mut default, &forecast, 2, &Settings::default()).unwrap();
+        let default = String::from_utf8(default).unwrap();
+
+        let theme = Theme::builtin("light-background").unwrap();
+        let mut light = Vec::new();
+        print_forecast(&mut light, &forecast, 2, &Settings { theme: Rc::new(theme), ..Settings::default() }).unwrap();
+        let light = String::from_utf8(light).unwrap();
+
+        // yellow sun and no frame colour by default
+        assert!(default.contains("\u{1b}[38;5;226m   \\  /"));
+        assert!(default.contains("\n┌───"));
+        assert!(light.contains("\u{1b}[38;5;172m   \\  /"));
+        assert!(light.contains("\n\u{1b}[38;5;244m┌───"));
+        assert_eq!(color::strip(&light), color::strip(&default));
+    }
+
+    #[test]
+    fn hourly() {
+        let forecast = sample_forecast();
+        let mut out = Vec::new();
+        print_hourly(&mut out, &forecast, 2, &Settings { color: false, ..Settings::default() }).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let rows: Vec<&str> = out.lines().filter(|l| l.starts_with(" 0") || l.starts_with(" 1") || l.starts_with(" 2")).collect();
+        assert_eq!(rows.len(), forecast.days.iter().map(|d| d.hourly.len()).sum::<usize>());
+        assert!(rows[9].starts_with(" 09:00  ☀☁   11 - 12 °C    → 13 - 23 km/h   0.0 mm | 0%     Partly cloudy"));
+        assert!(out.contains("──┤ Sun 18. Oct ├──"));
+
+        // the mini icons take the theme's colours too
+        let theme = Theme::builtin("light-background").unwrap();
+        let row = forecast.days[0].hourly[0].format_row(&Settings { theme: Rc::new(theme), ..Settings::default() });
+        assert!(row.contains("\u{1b}[38;5;172m ☀ "));
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(chart::sparkline(&[0.0, 5.0, 10.0, 20.0], (0.0, 10.0), ChartStyle::Ascii), ["_", "-", "^", "^"]);
+        assert_eq!(chart::sparkline(&[3.0, 3.0], (3.0, 3.0), ChartStyle::Unicode), ["▁", "▁"]);
+
+        let forecast = sample_forecast();
+        let mut out = Vec::new();
+        print_summary(&mut out, &forecast, 2, ChartStyle::Ascii, &Settings { color: false, ..Settings::default() }).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("Sun 18. Oct   ☁   6 - 18 °C     "));
+        assert!(lines[1].ends_with("  3.9 mm"));
+        // the coldest hour of both days is the lowest mark
+        assert!(lines[1].contains("._____."));
+        assert!(!lines[0].contains('_'));
+    }
+
+    #[test]
+    fn details() {
+        let forecast = sample_forecast();
+        let settings = Settings { details: true, color: false, ..Settings::default() };
+        assert_eq!(forecast.days[0].format_day(&settings)[0].len(), 8);
+
+        let mut out = Vec::new();
+        print_forecast(&mut out, &forecast, 2, &settings).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\n Humidity 60%  Clouds 80%     \n Dew point 6 °C               \n UV 4  1021 hPa  "));
+        assert!(out.contains("| Humidity 69%  Clouds 80%     | Humidity 62%  Clouds 80%     |"));
+        assert!(out.contains("| Dew point 7 °C               | Dew point 9 °C               |"));
+        for line in out.lines().filter(|l| l.starts_with('|')) {
+            assert_eq!(line.to_string().len_on_term(), WIDE, "{:?}", line);
+        }
+
+        // the longer names fit, each on its own line
+        for &(locale, lines) in &[(Locale::Fr, [" Humidité 69%  Nuages 80%     ", " Point de rosée 7 °C          "]),
+                                  (Locale::Es, [" Humedad 69%  Nubes 80%       ", " Punto de rocío 7 °C          "]),
+                                  (Locale::Ru, [" Влажность 69%  Облака 80%    ", " Точка росы 7 °C              "])] {
+            let mut out = Vec::new();
+            print_forecast(&mut out, &forecast, 1, &Settings { locale, ..settings.clone() }).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            for line in &lines {
+                assert!(out.contains(&format!("|{}|", line)), "{:?}: {:?}", locale, line);
+            }
+        }
+
+        // WHO categories: moderate is yellow, very high bold in monochrome
+        let uv = |uv, depth| colorized_uv(uv, &Settings { depth, ..Settings::default() });
+        assert_eq!(uv(4, ColorDepth::Ansi256), "\u{1b}[38;5;226m4\u{1b}[0m");
+        assert_eq!(uv(9, ColorDepth::Mono), "\u{1b}[1m9\u{1b}[0m");
+        assert_eq!(colorized_humidity(85, &settings), "\u{1b}[38;5;33m85%\u{1b}[0m");
+    }
+}
@@ -0,0 +1,136 @@
+// One-line output templates for status bars, e.g. "%c %t %w":
+//
+//     %c  condition icon       %C  condition description
+//     %t  temperature          %f  feels-like temperature
+//     %w  wind direction/speed %h  humidity
+//     %P  pressure             %p  precipitation
+//     %S  sunrise              %s  sunset
+//     %l  location             %%  a literal %
+//
+// The values are those of the current condition, sunrise and sunset those
+// of the first day. The output has no colours, status bars rarely show them.
+
+use color;
+use error::{Error, Result};
+use forecast::Forecast;
+use render::{self, Settings};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Field {
+    Icon,
+    Description,
+    Temperature,
+    FeelsLike,
+    Wind,
+    Humidity,
+    Pressure,
+    Precipitation,
+    Sunrise,
+    Sunset,
+    Location,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Template> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let field = match chars.next() {
+                Some('%') => {
+                    text.push('%');
+                    continue;
+                }
+                Some('c') => Field::Icon,
+                Some('C') => Field::Description,
+                Some('t') => Field::Temperature,
+                Some('f') => Field::FeelsLike,
+                Some('w') => Field::Wind,
+                Some('h') => Field::Humidity,
+                Some('P') => Field::Pressure,
+                Some('p') => Field::Precipitation,
+                Some('S') => Field::Sunrise,
+                Some('s') => Field::Sunset,
+                Some('l') => Field::Location,
+                Some(c)   => return Err(Error::Usage(format!("unknown placeholder in format: %{}", c))),
+                None      => return Err(Error::Usage("format ends with a lone %".to_string())),
+            };
+            if !text.is_empty() {
+                parts.push(Part::Text(text.clone()));
+                text.clear();
+            }
+            parts.push(Part::Field(field));
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn format(&self, forecast: &Forecast, settings: &Settings) -> String {
+        let now = &forecast.current;
+        let units = settings.units;
+        let astronomy = forecast.days.first().map(|d| &d.astronomy);
+        let time = |t: Option<&Option<String>>| t.and_then(|t| t.clone()).unwrap_or_else(|| "-".to_string());
+
+        self.parts.iter().map(|part| match *part {
+            Part::Text(ref text)               => text.clone(),
+            Part::Field(Field::Icon)           => color::strip(render::condition_to_mini_icon(now.condition))
+                .trim().to_string(),
+            Part::Field(Field::Description)    => now.description(settings.locale).to_string(),
+            Part::Field(Field::Temperature)    => format!("{}{}", units.temp(now.temp_c), units.temp_unit()),
+            Part::Field(Field::FeelsLike)      => format!("{}{}", units.temp(now.feels_like_c), units.temp_unit()),
+            Part::Field(Field::Wind)           => format!("{}{} {}",
+                                                          color::strip(render::wind_dir_to_icon(&now.wind_dir)),
+                                                          units.speed(now.wind_speed_kmph), units.speed_unit()),
+            Part::Field(Field::Humidity)       => format!("{}%", now.humidity),
+            Part::Field(Field::Pressure)       => now.pressure_hpa.map(|p| units.format_pressure(p))
+                .unwrap_or_else(|| "-".to_string()),
+            Part::Field(Field::Precipitation)  => units.format_precip(now.precip_mm),
+            Part::Field(Field::Sunrise)        => time(astronomy.map(|a| &a.sunrise)),
+            Part::Field(Field::Sunset)         => time(astronomy.map(|a| &a.sunset)),
+            Part::Field(Field::Location)       => forecast.location.clone(),
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::testing::sample_forecast;
+    use units::UnitSystem;
+
+    #[test]
+    fn templates() {
+        let forecast = sample_forecast();
+        let format = |s, settings: &Settings| Template::parse(s).unwrap().format(&forecast, settings);
+        let metric = Settings::default();
+
+        assert_eq!(format("%c %t %w", &metric), "☀☁ 14°C ↗12 km/h");
+        assert_eq!(format("%l: %C, feels %f, %h humidity", &metric),
+                   "Beijing, China: Partly cloudy, feels 12°C, 60% humidity");
+        assert_eq!(format("100%%", &metric), "100%");
+        assert_eq!(format("%S-%s %P %p", &metric), "06:31-17:31 1021 hPa 0.0 mm");
+        assert_eq!(format("%t %P", &Settings { units: UnitSystem::Imperial, ..Settings::default() }),
+                   "57°F 30.15 inHg");
+        assert_eq!(format("no fields", &metric), "no fields");
+
+        assert!(Template::parse("%x").is_err());
+        assert!(Template::parse("50%").is_err());
+    }
+}
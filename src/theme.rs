@@ -0,0 +1,338 @@
+// Colour themes. A theme file is TOML, every key is optional and falls back
+// to the `base` theme, "default" unless given:
+//
+//     base = "solarized-dark"
+//     sun = "#b58900"
+//     cloud = 250
+//     frame = "#586e75"
+//
+//     [[temperature]]
+//     from = -10
+//     color = "#268bd2"
+//
+//     [[temperature]]
+//     from = 25
+//     color = "#dc322f"
+//
+// Colours are xterm-256 indexes or "#rrggbb". Temperature steps are in °C,
+// wind steps in km/h, humidity steps in %, UV steps in UV index; each colour
+// applies from its `from` value on.
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use toml::{Parser, Table, Value};
+
+use color::Color;
+use error::{Error, Result};
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// `(from °C, colour)`, sorted.
+    pub temperature: Vec<(i32, Color)>,
+    /// `(from km/h, colour)`, sorted.
+    pub wind: Vec<(i32, Color)>,
+    /// `(from %, colour)`, sorted.
+    pub humidity: Vec<(i32, Color)>,
+    /// `(from UV index, colour)`, sorted.
+    pub uv: Vec<(i32, Color)>,
+    pub sun: Color,
+    pub cloud: Color,
+    pub dark_cloud: Color,
+    pub rain: Color,
+    pub heavy_rain: Color,
+    pub snow: Color,
+    pub lightning: Color,
+    pub fog: Color,
+    /// The day tables' lines, `None` for the terminal's own colour.
+    pub frame: Option<Color>,
+}
+
+pub static THEMES: [&'static str; 4] = ["default", "solarized-dark", "high-contrast", "light-background"];
+
+fn indexed(steps: &[(i32, u8)]) -> Vec<(i32, Color)> {
+    steps.iter().map(|&(from, i)| (from, Color::Indexed(i))).collect()
+}
+
+/// The WHO's UV index categories: low, moderate, high, very high, extreme.
+fn who_uv() -> Vec<(i32, Color)> {
+    indexed(&[(0, 40), (3, 226), (6, 208), (8, 196), (11, 129)])
+}
+
+fn rgb(hex: u32) -> Color {
+    Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            temperature: indexed(&[(-16, 21), (-15, 27), (-12, 33), (-9, 39), (-6, 45), (-3, 51),
+                                   (0, 50), (2, 49), (4, 48), (6, 47), (8, 46), (10, 82), (13, 118),
+                                   (16, 154), (19, 190), (22, 226), (25, 220), (28, 214), (31, 208),
+                                   (34, 202), (37, 196)]),
+            wind: indexed(&[(0, 46), (1, 82), (4, 118), (7, 154), (10, 190), (13, 226), (16, 220),
+                            (20, 214), (24, 208), (28, 202), (32, 196)]),
+            // dry, comfortable, humid, muggy
+            humidity: indexed(&[(0, 214), (30, 46), (60, 45), (80, 33)]),
+            uv: who_uv(),
+            sun: Color::Indexed(226),
+            cloud: Color::Indexed(250),
+            dark_cloud: Color::Indexed(240),
+            rain: Color::Indexed(111),
+            heavy_rain: Color::Indexed(21),
+            snow: Color::Indexed(255),
+            lightning: Color::Indexed(228),
+            fog: Color::Indexed(251),
+            frame: None,
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "default"          => Some(Theme::default()),
+            // https://ethanschoonover.com/solarized/
+            "solarized-dark"   => Some(Theme {
+                temperature: vec![(-16, rgb(0x6c71c4)), (-8, rgb(0x268bd2)), (0, rgb(0x2aa198)),
+                                  (10, rgb(0x859900)), (20, rgb(0xb58900)), (28, rgb(0xcb4b16)),
+                                  (35, rgb(0xdc322f))],
+                wind: vec![(0, rgb(0x2aa198)), (8, rgb(0x859900)), (16, rgb(0xb58900)),
+                           (24, rgb(0xcb4b16)), (32, rgb(0xdc322f))],
+                humidity: vec![(0, rgb(0xcb4b16)), (30, rgb(0x859900)), (60, rgb(0x2aa198)),
+                               (80, rgb(0x268bd2))],
+                uv: vec![(0, rgb(0x859900)), (3, rgb(0xb58900)), (6, rgb(0xcb4b16)),
+                         (8, rgb(0xdc322f)), (11, rgb(0x6c71c4))],
+                sun: rgb(0xb58900),
+                cloud: rgb(0x93a1a1),
+                dark_cloud: rgb(0x657b83),
+                rain: rgb(0x268bd2),
+                heavy_rain: rgb(0x6c71c4),
+                snow: rgb(0xeee8d5),
+                lightning: rgb(0xcb4b16),
+                fog: rgb(0x839496),
+                frame: Some(rgb(0x586e75)),
+            }),
+            // the 16 basic colours, bright variants only
+            "high-contrast"    => Some(Theme {
+                temperature: indexed(&[(-16, 12), (0, 14), (10, 10), (20, 11), (30, 9), (37, 13)]),
+                wind: indexed(&[(0, 10), (13, 11), (24, 9)]),
+                humidity: indexed(&[(0, 11), (30, 10), (60, 14), (80, 12)]),
+                uv: indexed(&[(0, 10), (3, 11), (6, 9), (11, 13)]),
+                sun: Color::Indexed(11),
+                cloud: Color::Indexed(15),
+                dark_cloud: Color::Indexed(7),
+                rain: Color::Indexed(14),
+                heavy_rain: Color::Indexed(12),
+                snow: Color::Indexed(15),
+                lightning: Color::Indexed(11),
+                fog: Color::Indexed(7),
+                frame: Some(Color::Indexed(15)),
+            }),
+            // dark colours for white or cream backgrounds
+            "light-background" => Some(Theme {
+                temperature: indexed(&[(-16, 18), (-8, 25), (0, 30), (10, 28), (18, 100), (24, 130),
+                                       (30, 124), (37, 88)]),
+                wind: indexed(&[(0, 28), (10, 100), (20, 130), (28, 124), (36, 88)]),
+                humidity: indexed(&[(0, 130), (30, 28), (60, 30), (80, 25)]),
+                uv: indexed(&[(0, 28), (3, 136), (6, 166), (8, 160), (11, 91)]),
+                sun: Color::Indexed(172),
+                cloud: Color::Indexed(244),
+                dark_cloud: Color::Indexed(238),
+                rain: Color::Indexed(25),
+                heavy_rain: Color::Indexed(18),
+                snow: Color::Indexed(67),
+                lightning: Color::Indexed(166),
+                fog: Color::Indexed(245),
+                frame: Some(Color::Indexed(244)),
+            }),
+            _                  => None,
+        }
+    }
+
+    /// A built-in theme, a theme file in $XDG_CONFIG_HOME/weather/themes/
+    /// ("mine" is themes/mine.toml), or the path of a theme file.
+    pub fn find(name: &str) -> Result<Theme> {
+        if let Some(theme) = Theme::builtin(name) {
+            return Ok(theme);
+        }
+        match themes_dir().map(|dir| dir.join(format!("{}.toml", name))) {
+            Some(ref path) if path.exists() => Theme::load(path),
+            _ if Path::new(name).exists()   => Theme::load(Path::new(name)),
+            _                               => Err(Error::Usage(format!("unknown theme: {}", name))),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Theme> {
+        let mut buf = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut buf))
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+        Theme::parse(&buf).map_err(|e| match e {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            e                  => e,
+        })
+    }
+
+    pub fn parse(s: &str) -> Result<Theme> {
+        let mut parser = Parser::new(s);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None        => {
+                let err = &parser.errors[0];
+                let (line, col) = parser.to_linecol(err.lo);
+                return Err(Error::Config(format!("{}:{}: {}", line + 1, col + 1, err.desc)));
+            }
+        };
+
+        let mut theme = match table.get("base") {
+            Some(Value::String(name)) => Theme::builtin(name)
+                .ok_or_else(|| Error::Config(format!("unknown base theme: {}", name)))?,
+            Some(_)                   => return Err(Error::Config("`base` must be a string".to_string())),
+            None                      => Theme::default(),
+        };
+
+        {
+            let colors = [("sun", &mut theme.sun), ("cloud", &mut theme.cloud),
+                          ("dark_cloud", &mut theme.dark_cloud), ("rain", &mut theme.rain),
+                          ("heavy_rain", &mut theme.heavy_rain), ("snow", &mut theme.snow),
+                          ("lightning", &mut theme.lightning), ("fog", &mut theme.fog)];
+            for (key, color) in colors {
+                if let Some(value) = table.get(key) {
+                    *color = color_value(key, value)?;
+                }
+            }
+        }
+        if let Some(value) = table.get("frame") {
+            theme.frame = Some(color_value("frame", value)?);
+        }
+        if let Some(steps) = steps_value(&table, "temperature")? {
+            theme.temperature = steps;
+        }
+        if let Some(steps) = steps_value(&table, "wind")? {
+            theme.wind = steps;
+        }
+        if let Some(steps) = steps_value(&table, "humidity")? {
+            theme.humidity = steps;
+        }
+        if let Some(steps) = steps_value(&table, "uv")? {
+            theme.uv = steps;
+        }
+        Ok(theme)
+    }
+
+    /// The icon tables are drawn in the default theme's xterm-256 colours,
+    /// this maps each of them to the part of the icon it stands for.
+    pub fn icon_color(&self, default: u8) -> Color {
+        match default {
+            226 => self.sun,
+            250 => self.cloud,
+            240 => self.dark_cloud,
+            111 => self.rain,
+            21  => self.heavy_rain,
+            255 => self.snow,
+            228 => self.lightning,
+            251 => self.fog,
+            i   => Color::Indexed(i),
+        }
+    }
+}
+
+fn themes_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _                                => env::home_dir()?.join(".config"),
+    };
+    Some(base.join("weather").join("themes"))
+}
+
+/// 0 to 255 or "#rrggbb".
+fn color_value(key: &str, value: &Value) -> Result<Color> {
+    match *value {
+        Value::Integer(i) if (0..256).contains(&i) => return Ok(Color::Indexed(i as u8)),
+        Value::String(ref s) if s.len() == 7 && s.starts_with('#') => {
+            if let Ok(hex) = u32::from_str_radix(&s[1..], 16) {
+                return Ok(rgb(hex));
+            }
+        }
+        _ => (),
+    }
+    Err(Error::Config(format!("`{}` must be a colour, 0 to 255 or \"#rrggbb\"", key)))
+}
+
+fn steps_value(table: &Table, key: &str) -> Result<Option<Vec<(i32, Color)>>> {
+    let err = || Error::Config(format!("`{}` must be a list of {{ from, color }} steps", key));
+    let steps = match table.get(key) {
+        Some(Value::Array(steps)) if !steps.is_empty() => steps,
+        Some(_)                                        => return Err(err()),
+        None                                           => return Ok(None),
+    };
+
+    let mut ret = Vec::with_capacity(steps.len());
+    for step in steps {
+        match (step.lookup("from"), step.lookup("color")) {
+            (Some(&Value::Integer(from)), Some(color)) => ret.push((from as i32, color_value(key, color)?)),
+            _                                           => return Err(err()),
+        }
+    }
+    ret.sort_by_key(|&(from, _)| from);
+    Ok(Some(ret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins() {
+        for name in THEMES.iter() {
+            let theme = Theme::builtin(name).unwrap();
+            assert!(theme.temperature.windows(2).all(|w| w[0].0 < w[1].0));
+            assert!(theme.wind.windows(2).all(|w| w[0].0 < w[1].0));
+            assert!(theme.humidity.windows(2).all(|w| w[0].0 < w[1].0));
+            assert!(theme.uv.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+        assert!(Theme::builtin("neon").is_none());
+        assert_eq!(Theme::default().icon_color(226), Color::Indexed(226));
+        assert_eq!(Theme::builtin("light-background").unwrap().icon_color(226), Color::Indexed(172));
+    }
+
+    #[test]
+    fn parse_theme_file() {
+        let theme = Theme::parse(r##"
+            base = "solarized-dark"
+            sun = 214
+            frame = "#ff0000"
+
+            [[wind]]
+            from = 20
+            color = 196
+
+            [[wind]]
+            from = 0
+            color = "#00ff00"
+
+            [[uv]]
+            from = 0
+            color = 22
+        "##).unwrap();
+
+        assert_eq!(theme.sun, Color::Indexed(214));
+        assert_eq!(theme.cloud, Color::Rgb(0x93, 0xa1, 0xa1));
+        assert_eq!(theme.frame, Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(theme.wind, vec![(0, Color::Rgb(0, 255, 0)), (20, Color::Indexed(196))]);
+        assert_eq!(theme.temperature, Theme::builtin("solarized-dark").unwrap().temperature);
+        assert_eq!(theme.uv, vec![(0, Color::Indexed(22))]);
+    }
+
+    #[test]
+    fn bad_theme_files() {
+        assert!(Theme::parse("sun = 300").is_err());
+        assert!(Theme::parse("sun = \"yellow\"").is_err());
+        assert!(Theme::parse("base = \"neon\"").is_err());
+        assert!(Theme::parse("temperature = 3").is_err());
+        assert!(Theme::parse("[[wind]]\ncolor = 1").is_err());
+        assert!(Theme::find("no-such-theme").is_err());
+    }
+}
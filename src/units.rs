@@ -0,0 +1,127 @@
+// Unit systems for display. The forecast model is always metric, values
+// are converted only when they're formatted.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnitSystem {
+    /// °C, km/h, km, mm
+    Metric,
+    /// °F, mph, mi, in
+    Imperial,
+    /// °C, m/s, km, mm
+    Si,
+    /// °C, mph, mi, mm
+    Uk,
+}
+
+pub static UNIT_SYSTEMS: [&'static str; 4] = ["metric", "imperial", "si", "uk"];
+
+impl UnitSystem {
+    pub fn from_name(name: &str) -> Option<UnitSystem> {
+        match name {
+            "metric"   => Some(UnitSystem::Metric),
+            "imperial" => Some(UnitSystem::Imperial),
+            "si"       => Some(UnitSystem::Si),
+            "uk"       => Some(UnitSystem::Uk),
+            _          => None,
+        }
+    }
+
+    pub fn temp(self, celsius: i32) -> i32 {
+        match self {
+            UnitSystem::Imperial => (celsius as f32 * 1.8 + 32.0).round() as i32,
+            _                    => celsius,
+        }
+    }
+
+    pub fn temp_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Imperial => "°F",
+            _                    => "°C",
+        }
+    }
+
+    pub fn speed(self, kmph: i32) -> i32 {
+        match self {
+            UnitSystem::Metric                    => kmph,
+            UnitSystem::Imperial | UnitSystem::Uk => (kmph as f32 / 1.609_344).round() as i32,
+            UnitSystem::Si                        => (kmph as f32 / 3.6).round() as i32,
+        }
+    }
+
+    pub fn speed_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric                    => "km/h",
+            UnitSystem::Imperial | UnitSystem::Uk => "mph",
+            UnitSystem::Si                        => "m/s",
+        }
+    }
+
+    pub fn distance(self, km: i32) -> i32 {
+        match self {
+            UnitSystem::Imperial | UnitSystem::Uk => (km as f32 / 1.609_344).round() as i32,
+            _                                     => km,
+        }
+    }
+
+    pub fn distance_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Imperial | UnitSystem::Uk => "mi",
+            _                                     => "km",
+        }
+    }
+
+    /// Precipitation with its unit, e.g. "0.4 mm" or "0.02 in".
+    pub fn format_precip(self, mm: f32) -> String {
+        match self {
+            UnitSystem::Imperial => format!("{:.2} in", mm / 25.4),
+            _                    => format!("{:.1} mm", mm),
+        }
+    }
+
+    /// Air pressure with its unit, e.g. "1015 hPa" or "29.97 inHg".
+    pub fn format_pressure(self, hpa: i32) -> String {
+        match self {
+            UnitSystem::Imperial => format!("{:.2} inHg", hpa as f32 * 0.029_53),
+            _                    => format!("{} hPa", hpa),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperatures() {
+        assert_eq!(UnitSystem::Imperial.temp(0), 32);
+        assert_eq!(UnitSystem::Imperial.temp(-40), -40);
+        assert_eq!(UnitSystem::Imperial.temp(22), 72);
+        assert_eq!(UnitSystem::Uk.temp(22), 22);
+    }
+
+    #[test]
+    fn speeds() {
+        assert_eq!(UnitSystem::Metric.speed(36), 36);
+        assert_eq!(UnitSystem::Si.speed(36), 10);
+        assert_eq!(UnitSystem::Imperial.speed(100), 62);
+        assert_eq!(UnitSystem::Uk.speed_unit(), "mph");
+    }
+
+    #[test]
+    fn distances_and_precipitation() {
+        assert_eq!(UnitSystem::Imperial.distance(10), 6);
+        assert_eq!(UnitSystem::Si.distance(10), 10);
+        assert_eq!(UnitSystem::Imperial.format_precip(12.7), "0.50 in");
+        assert_eq!(UnitSystem::Uk.format_precip(1.25), "1.2 mm");
+        assert_eq!(UnitSystem::Imperial.format_pressure(1015), "29.97 inHg");
+        assert_eq!(UnitSystem::Si.format_pressure(1015), "1015 hPa");
+    }
+
+    #[test]
+    fn names() {
+        for name in UNIT_SYSTEMS.iter() {
+            assert!(UnitSystem::from_name(name).is_some());
+        }
+        assert_eq!(UnitSystem::from_name("kelvin"), None);
+    }
+}